@@ -0,0 +1,297 @@
+use std::{cmp::min, fmt::Display};
+
+/// How hard a board is, independent of how it was played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardStats {
+    /// Bechtel's Board Benchmark Value: the least number of left clicks needed to clear
+    /// the board without chording. Each opening takes one click, as does each numbered
+    /// cell that isn't on the edge of an opening.
+    pub bbbv: usize,
+    /// Areas of empty cells, which open all at once.
+    pub openings: usize,
+    /// Groups of touching numbered cells away from any opening.
+    pub islands: usize,
+}
+
+impl BoardStats {
+    pub fn new(mines: &[Vec<bool>]) -> Self {
+        let regions = Regions::new(mines);
+        BoardStats {
+            bbbv: regions.bbbv(),
+            openings: regions.openings,
+            islands: regions.islands(),
+        }
+    }
+}
+
+/// How efficiently a game was played.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameStats {
+    pub board: BoardStats,
+    /// The part of the 3BV that has been cleared: the openings and the numbered cells away
+    /// from them that have been opened.
+    pub bbbv_solved: usize,
+    /// Opens, flags and chords, including ones that had no effect.
+    pub clicks: usize,
+    pub time: f64,
+}
+
+impl GameStats {
+    /// Solved 3BV per second.
+    pub fn bbbv_per_second(&self) -> f64 {
+        if self.time > 0.0 {
+            self.bbbv_solved as f64 / self.time
+        } else {
+            0.0
+        }
+    }
+
+    /// Index of efficiency: solved 3BV per click. Above 1 when chording saves clicks.
+    pub fn ioe(&self) -> f64 {
+        if self.clicks > 0 {
+            self.bbbv_solved as f64 / self.clicks as f64
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Display for GameStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "3BV {}/{}, {:.2} 3BV/s, {} clicks, IOE {:.2}",
+            self.bbbv_solved,
+            self.board.bbbv,
+            self.bbbv_per_second(),
+            self.clicks,
+            self.ioe()
+        )
+    }
+}
+
+/// Solved 3BV of a board where the cells in `opened` have been opened.
+pub fn bbbv_solved(mines: &[Vec<bool>], opened: &[Vec<bool>]) -> usize {
+    Regions::new(mines).bbbv_solved(opened)
+}
+
+/// A board split into its openings and the numbered cells outside them.
+struct Regions {
+    numbers: Vec<Vec<Option<u8>>>,
+    /// The opening each empty cell belongs to.
+    opening: Vec<Vec<Option<usize>>>,
+    /// Whether each cell is in or on the edge of an opening.
+    cleared: Vec<Vec<bool>>,
+    openings: usize,
+}
+
+impl Regions {
+    fn new(mines: &[Vec<bool>]) -> Self {
+        let numbers = numbers(mines);
+        let (width, height) = (mines[0].len(), mines.len());
+        let mut opening = vec![vec![None; width]; height];
+        let mut cleared = vec![vec![false; width]; height];
+        let mut openings = 0;
+        for y in 0..height {
+            for x in 0..width {
+                if numbers[y][x] != Some(0) || opening[y][x].is_some() {
+                    continue;
+                }
+                let mut to_clear = vec![(x, y)];
+                while let Some((x, y)) = to_clear.pop() {
+                    cleared[y][x] = true;
+                    if numbers[y][x] == Some(0) && opening[y][x].is_none() {
+                        opening[y][x] = Some(openings);
+                        to_clear.extend(neighbours(width, height, x, y));
+                    }
+                }
+                openings += 1;
+            }
+        }
+        Regions {
+            numbers,
+            opening,
+            cleared,
+            openings,
+        }
+    }
+
+    /// Numbered cells that aren't on the edge of an opening.
+    fn is_lone(&self, x: usize, y: usize) -> bool {
+        self.numbers[y][x].is_some() && !self.cleared[y][x]
+    }
+
+    fn bbbv(&self) -> usize {
+        let lone = self.cells().filter(|&(x, y)| self.is_lone(x, y)).count();
+        self.openings + lone
+    }
+
+    fn bbbv_solved(&self, opened: &[Vec<bool>]) -> usize {
+        let mut solved_openings = vec![false; self.openings];
+        let mut lone = 0;
+        for (x, y) in self.cells().filter(|&(x, y)| opened[y][x]) {
+            if let Some(i) = self.opening[y][x] {
+                solved_openings[i] = true;
+            } else if self.is_lone(x, y) {
+                lone += 1;
+            }
+        }
+        solved_openings.into_iter().filter(|&solved| solved).count() + lone
+    }
+
+    fn islands(&self) -> usize {
+        let (width, height) = (self.numbers[0].len(), self.numbers.len());
+        let mut seen = vec![vec![false; width]; height];
+        let mut islands = 0;
+        for (x, y) in self.cells() {
+            if !self.is_lone(x, y) || seen[y][x] {
+                continue;
+            }
+            islands += 1;
+            let mut to_visit = vec![(x, y)];
+            while let Some((x, y)) = to_visit.pop() {
+                if seen[y][x] {
+                    continue;
+                }
+                seen[y][x] = true;
+                let next = neighbours(width, height, x, y).filter(|&(x, y)| self.is_lone(x, y));
+                to_visit.extend(next);
+            }
+        }
+        islands
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (width, height) = (self.numbers[0].len(), self.numbers.len());
+        (0..height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+}
+
+/// The number on each cell, or `None` for mines.
+fn numbers(mines: &[Vec<bool>]) -> Vec<Vec<Option<u8>>> {
+    let (width, height) = (mines[0].len(), mines.len());
+    (0..height)
+        .map(|y| {
+            (0..width)
+                .map(|x| {
+                    (!mines[y][x]).then(|| {
+                        neighbours(width, height, x, y)
+                            .filter(|&(xn, yn)| mines[yn][xn])
+                            .count() as u8
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn neighbours(
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    (y.saturating_sub(1)..min(height, y + 2))
+        .flat_map(move |yn| (x.saturating_sub(1)..min(width, x + 2)).map(move |xn| (xn, yn)))
+        .filter(move |&cell| cell != (x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a layout with one row per line: `*` for mines, `.` otherwise.
+    fn layout(rows: &str) -> Vec<Vec<bool>> {
+        rows.lines()
+            .map(|row| row.trim().chars().map(|c| c == '*').collect())
+            .collect()
+    }
+
+    #[test]
+    fn board_stats() {
+        let rows = "..*..
+                    ..*..
+                    *****
+                    .*...
+                    ...*.";
+        let stats = BoardStats::new(&layout(rows));
+        // An opening in each top corner, and the eight numbered cells along the bottom
+        // all touch.
+        assert_eq!(
+            stats,
+            BoardStats {
+                bbbv: 10,
+                openings: 2,
+                islands: 1,
+            }
+        );
+        let stats = BoardStats::new(&layout("*.*.*"));
+        assert_eq!(
+            stats,
+            BoardStats {
+                bbbv: 2,
+                openings: 0,
+                islands: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn solved() {
+        let mines = layout("..*..\n..*..\n..*..");
+        let mut opened = vec![vec![false; 5]; 3];
+        assert_eq!(bbbv_solved(&mines, &opened), 0);
+        // A number on the edge of an opening doesn't solve it.
+        opened[0][1] = true;
+        assert_eq!(bbbv_solved(&mines, &opened), 0);
+        opened[1][0] = true;
+        assert_eq!(bbbv_solved(&mines, &opened), 1);
+
+        let mines = layout("....*\n...*.\n....*");
+        let mut opened = vec![vec![false; 5]; 3];
+        opened[1][4] = true;
+        assert_eq!(bbbv_solved(&mines, &opened), 1);
+    }
+
+    #[test]
+    fn efficiency() {
+        let stats = GameStats {
+            board: BoardStats::new(&layout("*.*\n...\n*.*")),
+            bbbv_solved: 5,
+            clicks: 10,
+            time: 2.0,
+        };
+        assert_eq!(stats.bbbv_per_second(), 2.5);
+        assert_eq!(stats.ioe(), 0.5);
+    }
+
+    #[test]
+    fn display() {
+        let stats = GameStats {
+            board: BoardStats::new(&layout("*.*\n...\n*.*")),
+            bbbv_solved: 4,
+            clicks: 5,
+            time: 4.0,
+        };
+        assert_eq!(stats.to_string(), "3BV 4/5, 1.00 3BV/s, 5 clicks, IOE 0.80");
+    }
+
+    #[test]
+    fn bbbv_counts() {
+        // One opening covers the whole board.
+        assert_eq!(BoardStats::new(&layout("*...\n....\n....")).bbbv, 1);
+        // Every safe cell is a number.
+        assert_eq!(BoardStats::new(&layout("*.*\n...\n*.*")).bbbv, 5);
+        // Two openings on either side of a wall, and the numbers between them are all on
+        // their edges.
+        let rows = "..*..
+                    ..*..
+                    ..*..";
+        assert_eq!(BoardStats::new(&layout(rows)).bbbv, 2);
+        // Numbers that no opening reaches need a click each.
+        let rows = "....*
+                    ...*.
+                    ....*";
+        assert_eq!(BoardStats::new(&layout(rows)).bbbv, 4);
+    }
+}
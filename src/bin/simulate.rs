@@ -0,0 +1,43 @@
+use clap::Parser;
+use minesweeper::{bot::Strategy, sim::Simulation, Difficulty, GeneratorKind, Settings};
+
+/// Plays games with the built-in bot and reports how it did.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Number of games to play.
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    games: usize,
+    /// easy, medium, hard or WIDTHxHEIGHT:MINES.
+    #[arg(short, long, default_value_t = Difficulty::Hard)]
+    difficulty: Difficulty,
+    #[arg(short, long, value_enum, default_value_t = GeneratorKind::Classic)]
+    generator: GeneratorKind,
+    /// Generate game i from SEED + i instead of a random seed.
+    #[arg(short, long)]
+    seed: Option<u64>,
+    /// Defaults to the number of CPUs.
+    #[arg(short = 'j', long)]
+    threads: Option<usize>,
+    /// How the bot guesses when it has to.
+    #[arg(long, value_enum, default_value_t = Strategy::Safest)]
+    guess: Strategy,
+}
+
+fn main() {
+    let args = Args::parse();
+    let settings = Settings {
+        difficulty: args.difficulty,
+        generator: args.generator,
+        seed: args.seed,
+        ..Settings::default()
+    };
+    let mut simulation = Simulation::new(settings, args.games).with_strategy(args.guess);
+    if let Some(threads) = args.threads {
+        simulation = simulation.with_threads(threads);
+    }
+    let report = simulation.run();
+    println!("{}", report);
+    if !report.errors.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+
+use crate::{solver, Action, Cell, Game, GameState};
+
+/// How the [`Bot`] picks a cell when the solver can't find a safe one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Strategy {
+    /// The cell least likely to be a mine.
+    #[default]
+    Safest,
+    /// Any hidden cell, chosen at random.
+    Random,
+}
+
+/// Plays games by opening the cells the solver proves safe and guessing when it is stuck.
+#[derive(Debug, Clone)]
+pub struct Bot {
+    strategy: Strategy,
+    flags: bool,
+    planned: VecDeque<Action>,
+    guesses: usize,
+}
+
+impl Bot {
+    pub fn new(strategy: Strategy) -> Self {
+        Bot {
+            strategy,
+            flags: true,
+            planned: VecDeque::new(),
+            guesses: 0,
+        }
+    }
+
+    /// Whether to flag the mines the solver finds. They don't need to be flagged to win, but
+    /// it is easier to follow.
+    pub fn with_flags(mut self, flags: bool) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// How many cells were opened without being known to be safe in the current game.
+    pub fn guesses(&self) -> usize {
+        self.guesses
+    }
+
+    /// The next action to take on `game`. Once the game has ended that is `Action::Reset`.
+    pub fn next_action(&mut self, game: &Game) -> Action {
+        match game.state() {
+            GameState::New => {
+                self.planned.clear();
+                self.guesses = 0;
+                Action::Open(game.width() / 2, game.height() / 2)
+            }
+            GameState::Playing => {
+                if let Some(action) = self.next_planned(game) {
+                    return action;
+                }
+                let deductions = solver::solve(game.board(), game.mines_remaining());
+                if self.flags {
+                    let flags = deductions.mines.iter().map(|&(x, y)| Action::Flag(x, y));
+                    self.planned.extend(flags);
+                }
+                let opens = deductions.safe.iter().map(|&(x, y)| Action::Open(x, y));
+                self.planned.extend(opens);
+                if let Some(action) = self.next_planned(game) {
+                    return action;
+                }
+                self.guesses += 1;
+                let (x, y) = self.guess(game);
+                Action::Open(x, y)
+            }
+            GameState::Won | GameState::Lost => Action::Reset,
+        }
+    }
+
+    /// Skips planned actions on cells that were opened since they were planned, such as by
+    /// an opening spreading.
+    fn next_planned(&mut self, game: &Game) -> Option<Action> {
+        while let Some(action) = self.planned.pop_front() {
+            match action {
+                Action::Open(x, y) | Action::Flag(x, y) if game.cell(x, y) == Cell::Hidden => {
+                    return Some(action)
+                }
+                _ => continue,
+            }
+        }
+        None
+    }
+
+    fn guess(&self, game: &Game) -> (usize, usize) {
+        let hidden: Vec<_> = game
+            .board()
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &cell)| (x, y, cell)))
+            .filter(|&(_, _, cell)| cell == Cell::Hidden)
+            .map(|(x, y, _)| (x, y))
+            .collect();
+        let safest = match self.strategy {
+            Strategy::Safest => solver::probabilities(game.board(), game.mines_remaining())
+                .and_then(|probabilities| probabilities.safest()),
+            Strategy::Random => None,
+        };
+        safest
+            .or_else(|| hidden.choose(&mut rand::thread_rng()).copied())
+            .expect("a game being played has hidden cells")
+    }
+}
+
+impl Default for Bot {
+    fn default() -> Self {
+        Bot::new(Strategy::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generator::SeededRandom, Difficulty, Settings};
+
+    fn play(bot: &mut Bot, game: &mut Game) {
+        for _ in 0..10_000 {
+            if game.state().has_ended() {
+                return;
+            }
+            let action = bot.next_action(game);
+            game.action(action).unwrap();
+        }
+        panic!("the bot never finished the game");
+    }
+
+    #[test]
+    fn plays_to_the_end() {
+        let settings = Settings {
+            difficulty: Difficulty::Medium,
+            ..Settings::default()
+        };
+        for strategy in [Strategy::Safest, Strategy::Random] {
+            let mut bot = Bot::new(strategy);
+            for seed in 0..5 {
+                let mut game = Game::with_generator(settings.clone(), SeededRandom::new(seed));
+                play(&mut bot, &mut game);
+                let mut cells = game.board().iter().flatten();
+                assert!(!cells.any(|&cell| cell == Cell::FalseFlag));
+                assert_eq!(bot.next_action(&game), Action::Reset);
+            }
+        }
+    }
+
+    #[test]
+    fn no_guesses_when_solvable() {
+        let settings = Settings {
+            difficulty: Difficulty::Easy,
+            generator: crate::GeneratorKind::NoGuess,
+            ..Settings::default()
+        };
+        let mut bot = Bot::new(Strategy::Safest).with_flags(false);
+        for seed in 0..5 {
+            let mut game = Game::new(Settings {
+                seed: Some(seed),
+                ..settings.clone()
+            });
+            play(&mut bot, &mut game);
+            assert_eq!(game.state(), GameState::Won);
+            assert_eq!(bot.guesses(), 0);
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::{collections::BTreeMap, fmt::Display, fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Difficulty, Settings};
+
+/// Preferences kept between runs in a TOML file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Name of the colour theme for the TUI.
+    pub theme: String,
+    /// Name of the key binding preset for the TUI: default, vi, wasd or numpad.
+    pub keymap: String,
+    /// The settings last saved in the settings pane, including the difficulty.
+    pub settings: Settings,
+    /// Custom boards by name, which can be given instead of a difficulty.
+    pub presets: BTreeMap<String, Difficulty>,
+    /// Key binding overrides for the TUI, from command name to key.
+    pub keys: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            theme: "classic".to_string(),
+            keymap: "default".to_string(),
+            settings: Settings::default(),
+            presets: BTreeMap::new(),
+            keys: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Write(toml::ser::Error),
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::Parse(err) => write!(f, "malformed config file: {}", err),
+            ConfigError::Write(err) => write!(f, "could not write config: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(err: toml::de::Error) -> Self {
+        ConfigError::Parse(err)
+    }
+}
+
+impl From<toml::ser::Error> for ConfigError {
+    fn from(err: toml::ser::Error) -> Self {
+        ConfigError::Write(err)
+    }
+}
+
+impl Config {
+    /// Reads the config at `path`, or the defaults if there is no file yet. Settings left
+    /// out of the file keep their defaults.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ConfigError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Stores `settings` as the ones to start with next time. A seed is only kept for the
+    /// run it was given in, so every later game isn't the same board.
+    pub fn set_settings(&mut self, settings: &Settings) {
+        self.settings = Settings {
+            seed: None,
+            ..settings.clone()
+        };
+    }
+
+    /// Looks `name` up in the presets, then parses it as a difficulty.
+    pub fn difficulty(&self, name: &str) -> Result<Difficulty, crate::ParseDifficultyError> {
+        match self.presets.get(name) {
+            Some(&difficulty) => Ok(difficulty),
+            None => name.parse(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomBoard, GeneratorKind};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("minesweeper-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = temp_path("config.toml");
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+
+        let mut config = Config::default();
+        config.set_settings(&Settings {
+            difficulty: Difficulty::Hard,
+            allow_undo: false,
+            seed: Some(3),
+            generator: GeneratorKind::NoGuess,
+        });
+        let wide = Difficulty::Custom(CustomBoard::new(40, 10, 60));
+        config.presets.insert("wide".to_string(), wide);
+        config.keymap = "vi".to_string();
+        config.keys.insert("flag".to_string(), "x".to_string());
+        config.save(&path).unwrap();
+        let loaded = Config::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, config);
+        assert_eq!(loaded.settings.seed, None);
+        assert_eq!(loaded.difficulty("wide"), Ok(wide));
+        assert_eq!(loaded.difficulty("medium"), Ok(Difficulty::Medium));
+        assert!(loaded.difficulty("tall").is_err());
+    }
+
+    #[test]
+    fn partial_file() {
+        let config: Config = toml::from_str(
+            r#"
+            [settings]
+            difficulty = "8x8:10"
+            "#,
+        )
+        .unwrap();
+        let difficulty = Difficulty::Custom(CustomBoard::new(8, 8, 10));
+        assert_eq!(config.settings.difficulty, difficulty);
+        assert!(config.settings.allow_undo);
+        assert_eq!(config.theme, Config::default().theme);
+        assert_eq!(config.keymap, "default");
+    }
+
+    #[test]
+    fn malformed_files() {
+        let errors = [
+            ("theme = ", "line 1, column 9"),
+            ("colour = \"red\"", "unknown field `colour`"),
+            (
+                "[settings]\ndifficulty = \"2x2:9\"",
+                "9 mines is more than 3",
+            ),
+        ];
+        for (contents, expected) in errors {
+            let err = ConfigError::from(toml::from_str::<Config>(contents).unwrap_err());
+            let msg = err.to_string();
+            assert!(msg.starts_with("malformed config file"), "{}", msg);
+            assert!(msg.contains(expected), "{}", msg);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt::Display;
+
+use super::generator::GenerateError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameError {
+    OutOfBounds {
+        x: usize,
+        y: usize,
+    },
+    AlreadyOpen {
+        x: usize,
+        y: usize,
+    },
+    Flagged {
+        x: usize,
+        y: usize,
+    },
+    NotHidden {
+        x: usize,
+        y: usize,
+    },
+    NotOpen {
+        x: usize,
+        y: usize,
+    },
+    GameOver,
+    NothingToChord {
+        x: usize,
+        y: usize,
+    },
+    InvalidChord {
+        x: usize,
+        y: usize,
+        flags: u8,
+        mines: u8,
+    },
+    TrippedMine {
+        x: usize,
+        y: usize,
+    },
+    InvalidBoard(BoardError),
+    Generation(GenerateError),
+    UndoDisabled,
+    NothingToUndo,
+    NothingToRedo,
+    UnhandledQuit,
+    UnhandledShowRecords,
+    UnhandledShowStats,
+    Paused,
+    NotPlaying,
+    NoHint,
+}
+
+impl std::error::Error for GameError {}
+
+impl Display for GameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GameError::OutOfBounds { x, y } => write!(f, "({}, {}) is out of bounds", x, y),
+            GameError::AlreadyOpen { x, y } => write!(f, "Cell ({}, {}) is already open", x, y),
+            GameError::Flagged { x, y } => write!(f, "Cell ({}, {}) is flagged", x, y),
+            GameError::NotHidden { x, y } => write!(f, "Cell ({}, {}) is not hidden", x, y),
+            GameError::NotOpen { x, y } => write!(f, "Cell ({}, {}) is not open", x, y),
+            GameError::GameOver => write!(f, "Game is over"),
+            GameError::NothingToChord { x, y } => {
+                write!(f, "Cell ({}, {}) has no mines around it to chord", x, y)
+            }
+            GameError::InvalidChord { x, y, flags, mines } => write!(
+                f,
+                "Can't chord ({}, {}): {} flags placed around a {}",
+                x, y, flags, mines
+            ),
+            GameError::TrippedMine { x, y } => write!(f, "Tripped a mine at ({}, {})", x, y),
+            GameError::InvalidBoard(err) => write!(f, "Invalid board: {}", err),
+            GameError::Generation(err) => write!(f, "Could not generate a board: {}", err),
+            GameError::UndoDisabled => write!(f, "Undo is disabled for this game"),
+            GameError::NothingToUndo => write!(f, "Nothing to undo"),
+            GameError::NothingToRedo => write!(f, "Nothing to redo"),
+            GameError::UnhandledQuit => write!(f, "Quit should be processed by the engine"),
+            GameError::UnhandledShowRecords => {
+                write!(f, "Showing records should be processed by the engine")
+            }
+            GameError::UnhandledShowStats => {
+                write!(f, "Showing stats should be processed by the engine")
+            }
+            GameError::Paused => write!(f, "Game is paused"),
+            GameError::NotPlaying => write!(f, "Only a game in progress can be paused"),
+            GameError::NoHint => write!(f, "No cell left to suggest"),
+        }
+    }
+}
+
+impl From<BoardError> for GameError {
+    fn from(err: BoardError) -> Self {
+        GameError::InvalidBoard(err)
+    }
+}
+
+impl From<GenerateError> for GameError {
+    fn from(err: GenerateError) -> Self {
+        GameError::Generation(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoardError {
+    WidthTooSmall {
+        width: usize,
+        min: usize,
+    },
+    HeightTooSmall {
+        height: usize,
+        min: usize,
+    },
+    TooFewMines {
+        mines: usize,
+        min: usize,
+    },
+    TooManyMines {
+        mines: usize,
+        max: usize,
+    },
+    AreaTooLarge {
+        area: usize,
+        max: usize,
+    },
+    LayoutMismatch {
+        width: usize,
+        height: usize,
+        mines: usize,
+    },
+}
+
+impl std::error::Error for BoardError {}
+
+impl Display for BoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BoardError::WidthTooSmall { width, min } => {
+                write!(f, "width {} is less than {}", width, min)
+            }
+            BoardError::HeightTooSmall { height, min } => {
+                write!(f, "height {} is less than {}", height, min)
+            }
+            BoardError::TooFewMines { mines, min } => {
+                write!(f, "{} mines is less than {}", mines, min)
+            }
+            BoardError::TooManyMines { mines, max } => {
+                write!(f, "{} mines is more than {} for this size", mines, max)
+            }
+            BoardError::AreaTooLarge { area, max } => {
+                write!(f, "{} cells is more than {}", area, max)
+            }
+            BoardError::LayoutMismatch {
+                width,
+                height,
+                mines,
+            } => write!(
+                f,
+                "mine layout doesn't match a {}x{} board with {} mines",
+                width, height, mines
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseDifficultyError {
+    Unknown(String),
+    Invalid(BoardError),
+}
+
+impl std::error::Error for ParseDifficultyError {}
+
+impl Display for ParseDifficultyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseDifficultyError::Unknown(s) => write!(
+                f,
+                "unknown difficulty '{}', expected easy, medium, hard or WIDTHxHEIGHT:MINES",
+                s
+            ),
+            ParseDifficultyError::Invalid(err) => write!(f, "invalid custom board: {}", err),
+        }
+    }
+}
+
+impl From<BoardError> for ParseDifficultyError {
+    fn from(err: BoardError) -> Self {
+        ParseDifficultyError::Invalid(err)
+    }
+}
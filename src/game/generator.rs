@@ -0,0 +1,453 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{rules, BoardError};
+use crate::solver;
+
+/// Places the mines for a new board.
+///
+/// Called once per game, when the first cell is opened. Implementations must return a
+/// `height` x `width` grid containing exactly `mines` mines.
+pub trait BoardGenerator: Send + Sync {
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        first_click: (usize, usize),
+    ) -> Result<Vec<Vec<bool>>, GenerateError>;
+
+    /// Whether the first cell opened is never a mine.
+    fn first_click_safe(&self) -> bool {
+        false
+    }
+}
+
+impl fmt::Debug for dyn BoardGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BoardGenerator")
+    }
+}
+
+impl<F> BoardGenerator for F
+where
+    F: Fn(usize, usize, usize, usize, usize) -> Vec<Vec<bool>> + Send + Sync,
+{
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        (firstx, firsty): (usize, usize),
+    ) -> Result<Vec<Vec<bool>>, GenerateError> {
+        Ok(self(width, height, mines, firstx, firsty))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerateError {
+    Invalid(BoardError),
+    /// No candidate layout could be solved without guessing.
+    NoBoardFound {
+        attempts: usize,
+    },
+}
+
+impl std::error::Error for GenerateError {}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenerateError::Invalid(err) => write!(f, "{}", err),
+            GenerateError::NoBoardFound { attempts } => write!(
+                f,
+                "no board that can be solved without guessing was found in {} attempts; \
+                 try fewer mines",
+                attempts
+            ),
+        }
+    }
+}
+
+impl From<BoardError> for GenerateError {
+    fn from(err: BoardError) -> Self {
+        GenerateError::Invalid(err)
+    }
+}
+
+/// The generators that can be chosen through [`Settings`](super::Settings).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum GeneratorKind {
+    /// Mines anywhere except under the first click.
+    #[default]
+    Classic,
+    /// No mines under or around the first click, so it always opens an area.
+    SafeRegion,
+    /// Like `SafeRegion`, but only boards that can be finished without guessing.
+    NoGuess,
+}
+
+impl GeneratorKind {
+    pub fn build(&self, seed: u64) -> Arc<dyn BoardGenerator> {
+        match self {
+            GeneratorKind::Classic => Arc::new(SeededRandom::new(seed)),
+            GeneratorKind::SafeRegion => Arc::new(SafeRegion::new(seed)),
+            GeneratorKind::NoGuess => Arc::new(NoGuess::new(seed)),
+        }
+    }
+}
+
+/// Uniformly random layouts that differ every game. The first click is never a mine.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UniformRandom;
+
+impl BoardGenerator for UniformRandom {
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        first_click: (usize, usize),
+    ) -> Result<Vec<Vec<bool>>, GenerateError> {
+        rules::validate(width, height, mines)?;
+        Ok(place_mines(
+            width,
+            height,
+            mines,
+            &[first_click],
+            &mut rand::thread_rng(),
+        ))
+    }
+
+    fn first_click_safe(&self) -> bool {
+        true
+    }
+}
+
+/// Uniformly random layouts that are the same for the same seed, size and first click. The
+/// first click is never a mine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeededRandom {
+    seed: u64,
+}
+
+impl SeededRandom {
+    pub fn new(seed: u64) -> Self {
+        SeededRandom { seed }
+    }
+}
+
+impl BoardGenerator for SeededRandom {
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        first_click: (usize, usize),
+    ) -> Result<Vec<Vec<bool>>, GenerateError> {
+        rules::validate(width, height, mines)?;
+        Ok(place_mines(
+            width,
+            height,
+            mines,
+            &[first_click],
+            &mut ChaCha8Rng::seed_from_u64(self.seed),
+        ))
+    }
+
+    fn first_click_safe(&self) -> bool {
+        true
+    }
+}
+
+/// Seeded random layouts with no mines on or around the first click. Falls back to keeping
+/// only the first click clear when the board is too full for that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafeRegion {
+    seed: u64,
+}
+
+impl SafeRegion {
+    pub fn new(seed: u64) -> Self {
+        SafeRegion { seed }
+    }
+}
+
+impl BoardGenerator for SafeRegion {
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        first_click: (usize, usize),
+    ) -> Result<Vec<Vec<bool>>, GenerateError> {
+        rules::validate(width, height, mines)?;
+        Ok(place_mines(
+            width,
+            height,
+            mines,
+            &safe_region(width, height, mines, first_click),
+            &mut ChaCha8Rng::seed_from_u64(self.seed),
+        ))
+    }
+
+    fn first_click_safe(&self) -> bool {
+        true
+    }
+}
+
+/// Seeded layouts like [`SafeRegion`] that the solver can clear from the first click
+/// without ever having to guess. Candidates are drawn until one is solvable, giving up with
+/// [`GenerateError::NoBoardFound`] after `max_attempts` candidates or `time_limit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoGuess {
+    seed: u64,
+    max_attempts: usize,
+    time_limit: Duration,
+}
+
+impl NoGuess {
+    pub const MAX_ATTEMPTS: usize = 100_000;
+    pub const TIME_LIMIT: Duration = Duration::from_secs(5);
+
+    pub fn new(seed: u64) -> Self {
+        NoGuess {
+            seed,
+            max_attempts: Self::MAX_ATTEMPTS,
+            time_limit: Self::TIME_LIMIT,
+        }
+    }
+
+    pub fn with_limits(mut self, max_attempts: usize, time_limit: Duration) -> Self {
+        self.max_attempts = max_attempts;
+        self.time_limit = time_limit;
+        self
+    }
+}
+
+impl BoardGenerator for NoGuess {
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        first_click: (usize, usize),
+    ) -> Result<Vec<Vec<bool>>, GenerateError> {
+        rules::validate(width, height, mines)?;
+        let started = Instant::now();
+        let excluded = safe_region(width, height, mines, first_click);
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let mut attempts = 0;
+        while attempts < self.max_attempts && started.elapsed() < self.time_limit {
+            attempts += 1;
+            let layout = place_mines(width, height, mines, &excluded, &mut rng);
+            if solver::solvable(&layout, first_click) {
+                return Ok(layout);
+            }
+        }
+        Err(GenerateError::NoBoardFound { attempts })
+    }
+
+    fn first_click_safe(&self) -> bool {
+        true
+    }
+}
+
+/// Always returns the same layout, such as one loaded from a replay.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedLayout {
+    mines: Vec<Vec<bool>>,
+}
+
+impl FixedLayout {
+    pub fn new(mines: Vec<Vec<bool>>) -> Self {
+        FixedLayout { mines }
+    }
+}
+
+impl BoardGenerator for FixedLayout {
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        mines: usize,
+        _first_click: (usize, usize),
+    ) -> Result<Vec<Vec<bool>>, GenerateError> {
+        rules::check_layout(&self.mines, width, height, mines)?;
+        Ok(self.mines.clone())
+    }
+}
+
+/// The first click and its neighbours, or only the first click if the rest of the board
+/// can't hold all the mines.
+fn safe_region(
+    width: usize,
+    height: usize,
+    mines: usize,
+    (firstx, firsty): (usize, usize),
+) -> Vec<(usize, usize)> {
+    let region: Vec<_> = (firsty.saturating_sub(1)..(firsty + 2).min(height))
+        .flat_map(|y| (firstx.saturating_sub(1)..(firstx + 2).min(width)).map(move |x| (x, y)))
+        .collect();
+    if width * height - region.len() >= mines {
+        region
+    } else {
+        vec![(firstx, firsty)]
+    }
+}
+
+/// Places `num_mines` mines uniformly at random on the cells not in `excluded`.
+pub(crate) fn place_mines<R: Rng + ?Sized>(
+    width: usize,
+    height: usize,
+    num_mines: usize,
+    excluded: &[(usize, usize)],
+    rng: &mut R,
+) -> Vec<Vec<bool>> {
+    let mut mines = vec![vec![false; width]; height];
+    let mut mines_left = num_mines;
+    let area = width * height;
+    for &(x, y) in excluded {
+        mines[y][x] = true;
+    }
+    let spaces = area - num_mines - excluded.len();
+
+    while mines_left > 0 {
+        let r = rng.gen_range(0..spaces + mines_left);
+        let mut i = 0;
+        for j in 0..area {
+            let (x, y) = (j % width, j / width);
+            if mines[y][x] {
+                continue;
+            }
+            if i == r {
+                mines[y][x] = true;
+                mines_left -= 1;
+                break;
+            }
+            i += 1;
+        }
+    }
+    for &(x, y) in excluded {
+        mines[y][x] = false;
+    }
+    mines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(mines: &[Vec<bool>]) -> usize {
+        mines.iter().flatten().filter(|&&mine| mine).count()
+    }
+
+    #[test]
+    fn uniform_random_basic() {
+        let mines = UniformRandom.generate(5, 4, 10, (0, 0)).unwrap();
+        assert_eq!(count(&mines), 10);
+    }
+
+    #[test]
+    fn random_does_not_get_first() {
+        for i in 0..5 {
+            let mines = UniformRandom.generate(5, 5, 24, (i, i)).unwrap();
+            assert!(!mines[i][i]);
+            let mines = SeededRandom::new(i as u64)
+                .generate(5, 5, 24, (i, i))
+                .unwrap();
+            assert!(!mines[i][i]);
+        }
+    }
+
+    #[test]
+    fn random_invalid_board() {
+        assert_eq!(
+            UniformRandom.generate(0, 0, 0, (0, 0)),
+            Err(GenerateError::Invalid(BoardError::WidthTooSmall {
+                width: 0,
+                min: 1
+            }))
+        );
+        assert_eq!(
+            SeededRandom::new(0).generate(5, 5, 25, (0, 0)),
+            Err(GenerateError::Invalid(BoardError::TooManyMines {
+                mines: 25,
+                max: 24
+            }))
+        );
+    }
+
+    #[test]
+    fn seeded_random_repeats() {
+        let generate = |seed| {
+            SeededRandom::new(seed)
+                .generate(16, 16, 40, (3, 4))
+                .unwrap()
+        };
+        assert_eq!(generate(42), generate(42));
+        assert_ne!(generate(42), generate(43));
+    }
+
+    #[test]
+    fn safe_region_is_clear() {
+        for seed in 0..20 {
+            let mines = SafeRegion::new(seed).generate(9, 9, 72, (0, 4)).unwrap();
+            assert_eq!(count(&mines), 72);
+            for (x, y) in [(0, 3), (1, 3), (0, 4), (1, 4), (0, 5), (1, 5)] {
+                assert!(!mines[y][x]);
+            }
+        }
+        // Too full for a safe region: only the first click stays clear.
+        let mines = SafeRegion::new(0).generate(3, 3, 8, (1, 1)).unwrap();
+        assert_eq!(count(&mines), 8);
+        assert!(!mines[1][1]);
+    }
+
+    #[test]
+    fn no_guess_is_solvable() {
+        for seed in 0..5 {
+            let mines = NoGuess::new(seed).generate(9, 9, 10, (4, 4)).unwrap();
+            assert_eq!(count(&mines), 10);
+            assert!(solver::solvable(&mines, (4, 4)));
+            assert_eq!(
+                NoGuess::new(seed).generate(9, 9, 10, (4, 4)).unwrap(),
+                mines
+            );
+        }
+    }
+
+    #[test]
+    fn no_guess_gives_up() {
+        // Two mines in a 2x2 board always leave a 50/50.
+        let generator = NoGuess::new(0).with_limits(20, Duration::from_secs(5));
+        assert_eq!(
+            generator.generate(2, 2, 2, (0, 0)),
+            Err(GenerateError::NoBoardFound { attempts: 20 })
+        );
+    }
+
+    #[test]
+    fn fixed_layout() {
+        let layout = vec![vec![true, false], vec![false, false]];
+        let generator = FixedLayout::new(layout.clone());
+        assert_eq!(generator.generate(2, 2, 1, (0, 0)), Ok(layout));
+        assert_eq!(
+            generator.generate(2, 2, 2, (0, 0)),
+            Err(GenerateError::Invalid(BoardError::LayoutMismatch {
+                width: 2,
+                height: 2,
+                mines: 2
+            }))
+        );
+    }
+}
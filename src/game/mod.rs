@@ -1,10 +1,25 @@
+mod error;
+pub mod generator;
+mod history;
 mod rules;
+mod save;
 mod timer;
 
-use std::cmp::min;
+use std::{cmp::min, fmt::Display, str::FromStr, sync::Arc};
 
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analysis::{self, BoardStats, GameStats},
+    solver,
+};
+
+pub use error::{BoardError, GameError, ParseDifficultyError};
+pub use generator::{BoardGenerator, GeneratorKind};
 pub use rules::GameState::{self, *};
+pub use save::SaveError;
 
+use history::{History, Snapshot};
 use rules::GameRules;
 use timer::Timer;
 
@@ -28,7 +43,7 @@ impl From<rules::Cell> for Cell {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Action {
     Quit,
     ChangeSettings(Settings),
@@ -37,6 +52,26 @@ pub enum Action {
     Flag(usize, usize),
     Chord(usize, usize),
     OpenOrChord(usize, usize),
+    Undo,
+    Redo,
+    Hint,
+    /// Shows the leaderboard for the current difficulty.
+    ShowRecords,
+    /// Shows the lifetime stats for every difficulty.
+    ShowStats,
+    /// Stops the timer. Moves, undo and hints are refused until [`Action::Resume`].
+    Pause,
+    Resume,
+}
+
+/// A cell suggested by [`Action::Hint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hint {
+    pub x: usize,
+    pub y: usize,
+    /// Whether the cell is certainly safe. Otherwise it is the cell least likely to be a
+    /// mine.
+    pub safe: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -46,37 +81,273 @@ pub struct Game {
     board: Vec<Vec<Cell>>,
     settings: Settings,
     mines_remaining: usize,
+    history: History,
+    seed: Option<u64>, // None when a generator was set with set_generator.
+    custom_generator: bool,
+    seeded: bool, // Whether the board comes from the seed in the settings.
+    hint: Option<Hint>,
+    hints_used: usize,
+    clicks: usize,
+    used_flags: bool,
+    used_undo: bool,
+    finished: bool,
+    paused: bool,
 }
 
 impl Game {
     pub fn new(settings: Settings) -> Self {
         let (width, height) = (settings.difficulty.width(), settings.difficulty.height());
         let mines = settings.difficulty.mines();
-        let rules = GameRules::new(width, height, mines);
+        let seeded = settings.seed.is_some();
+        let seed = settings.seed.unwrap_or_else(rand::random);
+        let mut rules = GameRules::new(width, height, mines);
+        rules.set_generator(settings.generator.build(seed));
         Game {
             rules,
             timer: Timer::new(),
             board: vec![vec![Cell::Hidden; width]; height],
             settings,
             mines_remaining: mines,
+            history: History::new(),
+            seed: Some(seed),
+            custom_generator: false,
+            seeded,
+            hint: None,
+            hints_used: 0,
+            clicks: 0,
+            used_flags: false,
+            used_undo: false,
+            finished: false,
+            paused: false,
+        }
+    }
+
+    pub fn with_generator(settings: Settings, generator: impl BoardGenerator + 'static) -> Self {
+        let mut game = Game::new(settings);
+        game.set_generator(Arc::new(generator));
+        game
+    }
+
+    /// Creates a game whose board is `mines` instead of a random layout, such as for replays.
+    /// The layout is kept on reset until the difficulty is changed.
+    pub fn with_layout(settings: Settings, mines: Vec<Vec<bool>>) -> Result<Self, BoardError> {
+        rules::check_layout(
+            &mines,
+            settings.difficulty.width(),
+            settings.difficulty.height(),
+            settings.difficulty.mines(),
+        )?;
+        Ok(Game::with_generator(
+            settings,
+            generator::FixedLayout::new(mines),
+        ))
+    }
+
+    /// Places the mines of the next boards with `generator` instead of the one selected in
+    /// the settings, until the difficulty is changed.
+    pub fn set_generator(&mut self, generator: Arc<dyn BoardGenerator>) {
+        self.seed = None;
+        self.custom_generator = true;
+        self.rules.set_generator(generator);
+    }
+
+    /// Goes back to the generator selected in the settings after `set_generator`.
+    pub fn use_settings_generator(&mut self) {
+        self.custom_generator = false;
+        self.new_seed();
+    }
+
+    fn new_seed(&mut self) {
+        if !self.custom_generator {
+            self.seeded = self.settings.seed.is_some();
+            self.set_seed(self.settings.seed.unwrap_or_else(rand::random));
         }
     }
 
-    pub fn action(&mut self, action: Action) -> Result<(), &'static str> {
-        match action {
+    /// Generates the next boards from `seed` with the generator selected in the settings.
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+        self.rules
+            .set_generator(self.settings.generator.build(seed));
+    }
+
+    pub fn action(&mut self, action: Action) -> Result<(), GameError> {
+        if self.paused {
+            if let Action::Open(..)
+            | Action::Flag(..)
+            | Action::Chord(..)
+            | Action::OpenOrChord(..)
+            | Action::Undo
+            | Action::Redo
+            | Action::Hint = action
+            {
+                return Err(GameError::Paused);
+            }
+        }
+        if action != Action::Hint {
+            self.hint = None;
+        }
+        if let Action::Open(..) | Action::Flag(..) | Action::Chord(..) | Action::OpenOrChord(..) =
+            action
+        {
+            if !self.state().has_ended() {
+                self.clicks += 1;
+            }
+        }
+        let result = match action {
             Action::ChangeSettings(settings) => self.change_settings(settings),
             Action::Reset => self.reset(),
-            Action::Open(x, y) => self.open(x, y),
-            Action::Flag(x, y) => self.flag(x, y),
-            Action::Quit => Err("Quit should be processed by the engine."),
-            Action::Chord(x, y) => self.chord(x, y),
-            Action::OpenOrChord(x, y) => self.open_or_chord(x, y),
+            Action::Open(x, y) => self.with_history(|game| game.open(x, y)),
+            Action::Flag(x, y) => self.with_history(|game| game.flag(x, y)),
+            Action::Quit => Err(GameError::UnhandledQuit),
+            Action::Chord(x, y) => self.with_history(|game| game.chord(x, y)),
+            Action::OpenOrChord(x, y) => self.with_history(|game| game.open_or_chord(x, y)),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Hint => self.give_hint(),
+            Action::ShowRecords => Err(GameError::UnhandledShowRecords),
+            Action::ShowStats => Err(GameError::UnhandledShowStats),
+            Action::Pause => self.pause(),
+            Action::Resume => self.resume(),
+        };
+        if self.state().has_ended() {
+            self.finished = true;
+        }
+        result
+    }
+
+    fn pause(&mut self) -> Result<(), GameError> {
+        if self.state() != Playing {
+            return Err(GameError::NotPlaying);
+        }
+        self.timer.stop();
+        self.paused = true;
+        Ok(())
+    }
+
+    fn resume(&mut self) -> Result<(), GameError> {
+        if self.paused {
+            self.paused = false;
+            self.timer.resume();
+        }
+        Ok(())
+    }
+
+    /// Finds a safe cell the player could work out from the board, or the best guess if
+    /// there is none. Flags are ignored, since they may be wrong.
+    fn give_hint(&mut self) -> Result<(), GameError> {
+        let hint = match self.state() {
+            New => Hint {
+                x: self.width() / 2,
+                y: self.height() / 2,
+                safe: self.rules.first_click_safe(),
+            },
+            Playing => {
+                let board: Vec<Vec<Cell>> = self
+                    .board
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|&cell| match cell {
+                                Cell::Flag => Cell::Hidden,
+                                cell => cell,
+                            })
+                            .collect()
+                    })
+                    .collect();
+                let deductions = solver::solve(&board, self.mines());
+                match deductions.safe.iter().next() {
+                    Some(&(x, y)) => Hint { x, y, safe: true },
+                    None => {
+                        let (x, y) = solver::probabilities(&board, self.mines())
+                            .and_then(|probabilities| probabilities.safest())
+                            .ok_or(GameError::NoHint)?;
+                        Hint { x, y, safe: false }
+                    }
+                }
+            }
+            Won | Lost => return Err(GameError::GameOver),
+        };
+        self.hint = Some(hint);
+        self.hints_used += 1;
+        Ok(())
+    }
+
+    fn with_history<F>(&mut self, f: F) -> Result<(), GameError>
+    where
+        F: FnOnce(&mut Self) -> Result<(), GameError>,
+    {
+        if !self.settings.allow_undo {
+            return f(self);
+        }
+        let before = self.snapshot();
+        let result = f(self);
+        if self.board != before.board {
+            self.history.record(before);
+        }
+        result
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            rules: self.rules.clone(),
+            board: self.board.clone(),
+            mines_remaining: self.mines_remaining,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.rules = snapshot.rules;
+        self.board = snapshot.board;
+        self.mines_remaining = snapshot.mines_remaining;
+        match self.state() {
+            New => self.timer.reset(),
+            Playing => {
+                if !self.timer.is_running() {
+                    self.timer.resume();
+                }
+            }
+            Won | Lost => self.timer.stop(),
+        }
+    }
+
+    fn undo(&mut self) -> Result<(), GameError> {
+        if !self.settings.allow_undo {
+            return Err(GameError::UndoDisabled);
+        }
+        let current = self.snapshot();
+        let previous = self.history.undo(current).ok_or(GameError::NothingToUndo)?;
+        self.restore(previous);
+        self.used_undo = true;
+        Ok(())
+    }
+
+    fn redo(&mut self) -> Result<(), GameError> {
+        if !self.settings.allow_undo {
+            return Err(GameError::UndoDisabled);
         }
+        let current = self.snapshot();
+        let next = self.history.redo(current).ok_or(GameError::NothingToRedo)?;
+        self.restore(next);
+        Ok(())
     }
 
-    fn change_settings(&mut self, settings: Settings) -> Result<(), &'static str> {
-        let should_reset = self.settings.difficulty != settings.difficulty;
+    fn change_settings(&mut self, settings: Settings) -> Result<(), GameError> {
+        let new_difficulty = self.settings.difficulty != settings.difficulty;
+        let should_reset = self.would_reset(&settings);
+        let new_generator = self.settings.generator != settings.generator;
         self.settings = settings;
+        if !self.settings.allow_undo {
+            self.history.clear();
+        }
+        if new_difficulty && self.custom_generator {
+            self.use_settings_generator();
+        }
+        if new_generator && !should_reset {
+            if let Some(seed) = self.seed {
+                self.set_seed(seed);
+            }
+        }
         if should_reset {
             let (width, height, num_mines) = (
                 self.settings.difficulty.width(),
@@ -89,15 +360,26 @@ impl Game {
         Ok(())
     }
 
-    fn reset(&mut self) -> Result<(), &'static str> {
+    fn reset(&mut self) -> Result<(), GameError> {
         self.timer.reset();
+        self.paused = false;
         self.board = vec![vec![Cell::Hidden; self.width()]; self.height()];
         self.rules.clear();
+        self.new_seed();
         self.mines_remaining = self.mines();
+        self.history.clear();
+        self.hints_used = 0;
+        self.clicks = 0;
+        self.used_flags = false;
+        self.used_undo = false;
+        self.finished = false;
         Ok(())
     }
 
-    fn open_or_chord(&mut self, x: usize, y: usize) -> Result<(), &'static str> {
+    fn open_or_chord(&mut self, x: usize, y: usize) -> Result<(), GameError> {
+        if !self.valid_coord(x, y) {
+            return Err(GameError::OutOfBounds { x, y });
+        }
         if let Cell::Open(_) = self.board[y][x] {
             self.chord(x, y)
         } else {
@@ -105,44 +387,41 @@ impl Game {
         }
     }
 
-    fn open(&mut self, x: usize, y: usize) -> Result<(), &'static str> {
+    fn open(&mut self, x: usize, y: usize) -> Result<(), GameError> {
         if !self.valid_coord(x, y) {
-            return Err("invalid coordinate");
-        }
-        if self.state() == New {
-            self.timer.start();
+            return Err(GameError::OutOfBounds { x, y });
         }
-
-        if self.state() == Lost || self.state() == Won {
-            return Err("Game is over");
+        if self.state().has_ended() {
+            return Err(GameError::GameOver);
         }
 
         match self.board[y][x] {
-            Cell::Flag => return Err("Cell if flagged"),
-            Cell::Open(_) => return Err("Cell is already open"),
+            Cell::Flag => Err(GameError::Flagged { x, y }),
+            Cell::Open(_) => Err(GameError::AlreadyOpen { x, y }),
             Cell::Hidden => {
-                match self.rules.open(x, y) {
-                    Err(err) => {
-                        panic!("Unreachable: fail conditions already checked got {:?}", err)
-                    }
-                    Ok(info) => match info {
-                        rules::OpenInfo {
-                            state: Playing,
-                            cell,
-                        } => {
-                            self.board[y][x] = cell.into();
-                            if cell == rules::Cell::Clear(0) {
-                                self.open_neighbors(x, y)?;
-                            }
+                let starting = self.state() == New;
+                let info = self.rules.open(x, y)?;
+                // Only start the clock once the board is generated, which can take a while.
+                if starting {
+                    self.timer.start();
+                }
+                match info {
+                    rules::OpenInfo {
+                        state: Playing,
+                        cell,
+                    } => {
+                        self.board[y][x] = cell.into();
+                        if cell == rules::Cell::Clear(0) {
+                            self.open_neighbors(x, y)?;
                         }
-                        rules::OpenInfo { state: _, cell } => {
-                            if cell == rules::Cell::Mine {
-                                self.board[y][x] = Cell::TrippedMine;
-                            }
-                            self.query_board();
-                            self.timer.stop();
+                    }
+                    rules::OpenInfo { state: _, cell } => {
+                        if cell == rules::Cell::Mine {
+                            self.board[y][x] = Cell::TrippedMine;
                         }
-                    },
+                        self.query_board();
+                        self.timer.stop();
+                    }
                 };
                 Ok(())
             }
@@ -152,26 +431,27 @@ impl Game {
         }
     }
 
-    fn open_neighbors(&mut self, x: usize, y: usize) -> Result<(), &'static str> {
+    fn open_neighbors(&mut self, x: usize, y: usize) -> Result<(), GameError> {
         for (xn, yn) in self.neighbours(x, y) {
             if self.board[yn][xn] == Cell::Hidden {
                 self.open(xn, yn)?;
             }
             if self.board[yn][xn] == Cell::TrippedMine {
-                return Err("Auto-tripped on mine.");
+                return Err(GameError::TrippedMine { x: xn, y: yn });
             }
         }
         Ok(())
     }
 
-    fn flag(&mut self, x: usize, y: usize) -> Result<(), &'static str> {
+    fn flag(&mut self, x: usize, y: usize) -> Result<(), GameError> {
         if !self.valid_coord(x, y) {
-            return Err("invalid coordinate");
+            return Err(GameError::OutOfBounds { x, y });
         }
         match self.board[y][x] {
             Cell::Hidden => {
                 self.board[y][x] = Cell::Flag;
                 self.mines_remaining -= 1;
+                self.used_flags = true;
                 Ok(())
             }
             Cell::Flag => {
@@ -179,23 +459,23 @@ impl Game {
                 self.mines_remaining += 1;
                 Ok(())
             }
-            _ => Err("Cell not hidden"),
+            _ => Err(GameError::NotHidden { x, y }),
         }
     }
 
-    fn chord(&mut self, x: usize, y: usize) -> Result<(), &'static str> {
+    fn chord(&mut self, x: usize, y: usize) -> Result<(), GameError> {
         if !self.valid_coord(x, y) {
-            return Err("invalid coordinate");
+            return Err(GameError::OutOfBounds { x, y });
         }
         match self.board[y][x] {
-            Cell::Flag => Err("Cell is flagged"),
-            Cell::Hidden => Err("Cell is hidden"),
+            Cell::Flag => Err(GameError::Flagged { x, y }),
+            Cell::Hidden => Err(GameError::NotOpen { x, y }),
             Cell::Open(mines) => {
                 if self.state() != Playing {
-                    return Err("Game is over");
+                    return Err(GameError::GameOver);
                 }
                 if mines == 0 {
-                    return Err("0s get auto-chorded.");
+                    return Err(GameError::NothingToChord { x, y });
                 }
                 let mut neighbouring_flags = 0;
                 for (x, y) in self.neighbours(x, y) {
@@ -206,10 +486,15 @@ impl Game {
                 if neighbouring_flags == mines {
                     self.open_neighbors(x, y)
                 } else {
-                    Err("Can't chord this cell. Incorrect number of flags.")
+                    Err(GameError::InvalidChord {
+                        x,
+                        y,
+                        flags: neighbouring_flags,
+                        mines,
+                    })
                 }
             }
-            _ => Err("Game is over"),
+            _ => Err(GameError::GameOver),
         }
     }
 
@@ -253,6 +538,88 @@ impl Game {
         self.mines_remaining
     }
 
+    /// The seed the board is generated from. Boards with the same seed, generator, size,
+    /// number of mines and first click are identical. `None` if a generator was set with
+    /// `set_generator`.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// The cell suggested by the last action, if it was a hint.
+    pub fn hint(&self) -> Option<Hint> {
+        self.hint
+    }
+
+    /// How many hints have been given since the game started.
+    pub fn hints_used(&self) -> usize {
+        self.hints_used
+    }
+
+    /// How many cells have been opened, flagged or chorded since the game started,
+    /// including attempts that failed.
+    pub fn clicks(&self) -> usize {
+        self.clicks
+    }
+
+    /// Whether a flag has been placed since the game started, even if it was removed.
+    pub fn used_flags(&self) -> bool {
+        self.used_flags
+    }
+
+    /// Whether `action` opens the first cell of the game, which generates the board.
+    pub fn generates_board(&self, action: &Action) -> bool {
+        match *action {
+            Action::Open(x, y) | Action::OpenOrChord(x, y) => {
+                self.state() == New && !self.paused && self.valid_coord(x, y)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether changing to `settings` would throw away the current game and start a new one.
+    pub fn would_reset(&self, settings: &Settings) -> bool {
+        self.settings.difficulty != settings.difficulty
+            || (settings.seed.is_some() && self.settings.seed != settings.seed)
+    }
+
+    /// Whether the board could be known before the game started, because it comes from a
+    /// seed in the settings or a generator set with `set_generator`.
+    pub fn board_is_chosen(&self) -> bool {
+        self.seeded || self.custom_generator
+    }
+
+    /// Whether a move has been undone since the game started.
+    pub fn used_undo(&self) -> bool {
+        self.used_undo
+    }
+
+    /// Whether the game has been won or lost since it started. Unlike
+    /// `state().has_ended()`, undoing the last move doesn't clear it, so each game is only
+    /// counted once.
+    pub fn has_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// 3BV, efficiency and other measures of the game, once the board has been generated.
+    pub fn stats(&self) -> Option<GameStats> {
+        let (mines, opened) = (self.rules.layout()?, self.rules.opened()?);
+        Some(GameStats {
+            board: BoardStats::new(mines),
+            bbbv_solved: analysis::bbbv_solved(mines, opened),
+            clicks: self.clicks,
+            time: self.time(),
+        })
+    }
+
+    /// The mine layout, once it has been generated by the first click.
+    pub(crate) fn layout(&self) -> Option<&Vec<Vec<bool>>> {
+        self.rules.layout()
+    }
+
     fn query_board(&mut self) {
         match self.state() {
             Won | Lost => {
@@ -287,7 +654,8 @@ impl Default for Game {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
 pub enum Difficulty {
     Easy,
     Medium,
@@ -314,6 +682,10 @@ impl Difficulty {
         }
     }
 
+    pub fn try_custom(width: usize, height: usize, mines: usize) -> Result<Self, BoardError> {
+        CustomBoard::try_new(width, height, mines).map(Difficulty::Custom)
+    }
+
     pub fn mines(&self) -> usize {
         match self {
             Difficulty::Easy => 10,
@@ -324,6 +696,57 @@ impl Difficulty {
     }
 }
 
+impl Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "easy"),
+            Difficulty::Medium => write!(f, "medium"),
+            Difficulty::Hard => write!(f, "hard"),
+            Difficulty::Custom(board) => {
+                write!(f, "{}x{}:{}", board.width, board.height, board.mines)
+            }
+        }
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = ParseDifficultyError;
+
+    /// Parses `easy`, `medium`, `hard` or a custom board written as `WIDTHxHEIGHT:MINES`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || ParseDifficultyError::Unknown(s.to_string());
+        match s.trim().to_ascii_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            custom => {
+                let (size, mines) = custom.split_once(':').ok_or_else(unknown)?;
+                let (width, height) = size.split_once('x').ok_or_else(unknown)?;
+                let parse = |n: &str| n.trim().parse::<usize>().map_err(|_| unknown());
+                Ok(Difficulty::try_custom(
+                    parse(width)?,
+                    parse(height)?,
+                    parse(mines)?,
+                )?)
+            }
+        }
+    }
+}
+
+impl From<Difficulty> for String {
+    fn from(difficulty: Difficulty) -> Self {
+        difficulty.to_string()
+    }
+}
+
+impl TryFrom<String> for Difficulty {
+    type Error = ParseDifficultyError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct CustomBoard {
     width: usize,
@@ -332,32 +755,42 @@ pub struct CustomBoard {
 }
 
 impl CustomBoard {
+    pub const MIN_SIDE: usize = rules::MIN_SIDE;
+    pub const MIN_MINES: usize = rules::MIN_MINES;
+    pub const MAX_AREA: usize = rules::MAX_AREA;
+
     pub fn new(width: usize, height: usize, mines: usize) -> Self {
-        if width < 1 || height < 1 {
-            panic!("Width and height must be at least 1")
-        } else if mines < 1 {
-            panic!("Mines must be at least 1")
-        } else if mines >= width * height {
-            panic!("Mines must be less than the number of cells")
-        } else {
-            CustomBoard {
-                width,
-                height,
-                mines,
-            }
-        }
+        Self::try_new(width, height, mines).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(width: usize, height: usize, mines: usize) -> Result<Self, BoardError> {
+        rules::validate(width, height, mines)?;
+        Ok(CustomBoard {
+            width,
+            height,
+            mines,
+        })
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Settings {
     pub difficulty: Difficulty,
+    /// Disable for ranked play, where taking back a move shouldn't be possible.
+    pub allow_undo: bool,
+    /// Generate every board from this seed instead of a random one.
+    pub seed: Option<u64>,
+    pub generator: GeneratorKind,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             difficulty: Difficulty::Easy,
+            allow_undo: true,
+            seed: None,
+            generator: GeneratorKind::default(),
         }
     }
 }
@@ -398,3 +831,408 @@ impl Iterator for NeighboursIter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn corner_mines(
+        width: usize,
+        height: usize,
+        _num_mines: usize,
+        _firstx: usize,
+        _firsty: usize,
+    ) -> Vec<Vec<bool>> {
+        let mut mines = vec![vec![false; width]; height];
+        mines[0][0] = true;
+        mines[height - 1][width - 1] = true;
+        mines
+    }
+
+    fn corner_game() -> Game {
+        Game::with_generator(
+            Settings {
+                difficulty: Difficulty::Custom(CustomBoard::new(3, 3, 2)),
+                ..Settings::default()
+            },
+            corner_mines,
+        )
+    }
+
+    #[test]
+    fn custom_board_validation() {
+        assert_eq!(
+            CustomBoard::try_new(0, 3, 1),
+            Err(BoardError::WidthTooSmall { width: 0, min: 1 })
+        );
+        assert_eq!(
+            Difficulty::try_custom(4, 4, 16),
+            Err(BoardError::TooManyMines { mines: 16, max: 15 })
+        );
+        assert_eq!(
+            Difficulty::try_custom(30, 16, 99),
+            Ok(Difficulty::Custom(CustomBoard::new(30, 16, 99)))
+        );
+        assert_eq!(
+            Difficulty::try_custom(usize::MAX, usize::MAX, 1),
+            Err(BoardError::AreaTooLarge {
+                area: usize::MAX,
+                max: 99 * 99
+            })
+        );
+    }
+
+    #[test]
+    fn parse_difficulty() {
+        assert_eq!("Hard".parse(), Ok(Difficulty::Hard));
+        assert_eq!(
+            "30x20:120".parse(),
+            Ok(Difficulty::Custom(CustomBoard::new(30, 20, 120)))
+        );
+        assert_eq!(
+            "3x3:9".parse::<Difficulty>(),
+            Err(ParseDifficultyError::Invalid(BoardError::TooManyMines {
+                mines: 9,
+                max: 8
+            }))
+        );
+        assert_eq!(
+            "expert".parse::<Difficulty>(),
+            Err(ParseDifficultyError::Unknown("expert".to_string()))
+        );
+        let custom = Difficulty::Custom(CustomBoard::new(12, 7, 20));
+        assert_eq!(custom.to_string().parse(), Ok(custom));
+    }
+
+    #[test]
+    #[should_panic]
+    fn custom_board_panics() {
+        CustomBoard::new(3, 3, 0);
+    }
+
+    #[test]
+    fn seeded_boards_repeat() {
+        let settings = Settings {
+            difficulty: Difficulty::Medium,
+            seed: Some(1234),
+            ..Settings::default()
+        };
+        let mut game = Game::new(settings.clone());
+        assert_eq!(game.seed(), Some(1234));
+        game.action(Action::Open(5, 5)).unwrap();
+        let layout = game.layout().cloned();
+        game.action(Action::Reset).unwrap();
+        game.action(Action::Open(5, 5)).unwrap();
+        assert_eq!(game.layout().cloned(), layout);
+
+        let mut other = Game::new(settings);
+        other.action(Action::Open(5, 5)).unwrap();
+        assert_eq!(other.board(), game.board());
+    }
+
+    #[test]
+    fn generator_from_settings() {
+        let settings = Settings {
+            difficulty: Difficulty::Hard,
+            generator: GeneratorKind::SafeRegion,
+            ..Settings::default()
+        };
+        for _ in 0..10 {
+            let mut game = Game::new(settings.clone());
+            game.action(Action::Open(0, 0)).unwrap();
+            assert_eq!(game.cell(0, 0), Cell::Open(0));
+        }
+    }
+
+    #[test]
+    fn custom_generator() {
+        let mut game = Game::with_generator(corner_game().settings().clone(), corner_mines);
+        assert_eq!(game.seed(), None);
+        game.action(Action::Open(2, 0)).unwrap();
+        assert_eq!(game.layout(), Some(&corner_mines(3, 3, 2, 2, 0)));
+        game.action(Action::Reset).unwrap();
+        game.action(Action::Open(2, 0)).unwrap();
+        assert_eq!(game.layout(), Some(&corner_mines(3, 3, 2, 2, 0)));
+
+        game.action(Action::ChangeSettings(Settings::default()))
+            .unwrap();
+        assert!(game.seed().is_some());
+    }
+
+    #[test]
+    fn hints() {
+        let mut game = Game::new(Settings::default());
+        game.action(Action::Hint).unwrap();
+        assert_eq!(
+            game.hint(),
+            Some(Hint {
+                x: 4,
+                y: 4,
+                safe: true
+            })
+        );
+
+        // Any cell could be a mine on a fixed layout.
+        let mut game = corner_game();
+        game.action(Action::Hint).unwrap();
+        assert_eq!(
+            game.hint(),
+            Some(Hint {
+                x: 1,
+                y: 1,
+                safe: false
+            })
+        );
+        game.action(Action::Open(1, 1)).unwrap();
+        assert_eq!(game.hint(), None);
+
+        // Only the 2 in the middle is open, so every cell is a guess.
+        game.action(Action::Hint).unwrap();
+        let hint = game.hint().unwrap();
+        assert!(!hint.safe);
+        assert_eq!(game.cell(hint.x, hint.y), Cell::Hidden);
+
+        assert_eq!(game.hints_used(), 2);
+        game.action(Action::Reset).unwrap();
+        assert_eq!(game.hints_used(), 0);
+        game.action(Action::Open(1, 1)).unwrap();
+        game.action(Action::Open(0, 0)).unwrap();
+        assert_eq!(game.action(Action::Hint), Err(GameError::GameOver));
+    }
+
+    #[test]
+    fn safe_hint_ignores_flags() {
+        let mut mines = vec![vec![false; 3]; 3];
+        mines[0][0] = true;
+        let settings = Settings {
+            difficulty: Difficulty::Custom(CustomBoard::new(3, 3, 1)),
+            ..Settings::default()
+        };
+        let mut game = Game::with_layout(settings, mines).unwrap();
+        game.action(Action::Open(1, 0)).unwrap();
+        // Wrong, but the hint mustn't trust it.
+        game.action(Action::Flag(2, 0)).unwrap();
+        game.action(Action::Hint).unwrap();
+        let hint = game.hint().unwrap();
+        assert!(hint.safe);
+        assert_eq!(hint.y, 2);
+    }
+
+    #[test]
+    fn stats() {
+        let mut game = corner_game();
+        assert_eq!(game.stats(), None);
+        game.action(Action::Open(1, 1)).unwrap();
+        game.action(Action::Flag(0, 0)).unwrap();
+        game.action(Action::Open(1, 1)).unwrap_err();
+        // The two empty corners are openings that cover all the numbers.
+        let stats = game.stats().unwrap();
+        assert_eq!(stats.board.bbbv, 2);
+        assert_eq!(stats.bbbv_solved, 0);
+        assert_eq!(stats.clicks, 3);
+
+        game.action(Action::Undo).unwrap();
+        game.action(Action::Hint).unwrap();
+        assert_eq!(game.clicks(), 3);
+        assert!(game.used_flags());
+        game.action(Action::Open(2, 0)).unwrap();
+        assert_eq!(game.stats().unwrap().bbbv_solved, 1);
+        game.action(Action::Open(2, 2)).unwrap();
+        game.action(Action::Open(0, 0)).unwrap_err();
+        assert_eq!(game.clicks(), 5);
+        game.action(Action::Reset).unwrap();
+        assert_eq!(game.clicks(), 0);
+        assert!(!game.used_flags());
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let mut game = Game::default();
+        assert_eq!(
+            game.action(Action::Open(9, 0)),
+            Err(GameError::OutOfBounds { x: 9, y: 0 })
+        );
+        assert_eq!(
+            game.action(Action::Flag(0, 9)),
+            Err(GameError::OutOfBounds { x: 0, y: 9 })
+        );
+        assert_eq!(
+            game.action(Action::OpenOrChord(9, 9)),
+            Err(GameError::OutOfBounds { x: 9, y: 9 })
+        );
+        assert_eq!(game.action(Action::Quit), Err(GameError::UnhandledQuit));
+        assert_eq!(
+            game.action(Action::ShowRecords),
+            Err(GameError::UnhandledShowRecords)
+        );
+        assert_eq!(
+            game.action(Action::ShowStats),
+            Err(GameError::UnhandledShowStats)
+        );
+    }
+
+    #[test]
+    fn cell_errors() {
+        let mut game = corner_game();
+        assert_eq!(
+            game.action(Action::Chord(1, 0)),
+            Err(GameError::NotOpen { x: 1, y: 0 })
+        );
+        game.action(Action::Flag(0, 1)).unwrap();
+        assert_eq!(
+            game.action(Action::Open(0, 1)),
+            Err(GameError::Flagged { x: 0, y: 1 })
+        );
+        game.action(Action::Open(1, 0)).unwrap();
+        assert_eq!(game.cell(1, 0), Cell::Open(1));
+        assert_eq!(
+            game.action(Action::Open(1, 0)),
+            Err(GameError::AlreadyOpen { x: 1, y: 0 })
+        );
+        assert_eq!(
+            game.action(Action::Flag(1, 0)),
+            Err(GameError::NotHidden { x: 1, y: 0 })
+        );
+        game.action(Action::Flag(0, 1)).unwrap();
+        assert_eq!(
+            game.action(Action::Chord(1, 0)),
+            Err(GameError::InvalidChord {
+                x: 1,
+                y: 0,
+                flags: 0,
+                mines: 1
+            })
+        );
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut game = corner_game();
+        assert_eq!(game.action(Action::Undo), Err(GameError::NothingToUndo));
+        game.action(Action::Open(1, 0)).unwrap();
+        game.action(Action::Flag(2, 2)).unwrap();
+        assert_eq!(game.mines_remaining(), 1);
+
+        game.action(Action::Undo).unwrap();
+        assert_eq!(game.cell(2, 2), Cell::Hidden);
+        assert_eq!(game.mines_remaining(), 2);
+        game.action(Action::Redo).unwrap();
+        assert_eq!(game.cell(2, 2), Cell::Flag);
+        assert_eq!(game.action(Action::Redo), Err(GameError::NothingToRedo));
+
+        // Errors don't add to the history.
+        assert!(game.action(Action::Open(1, 0)).is_err());
+        game.action(Action::Undo).unwrap();
+        game.action(Action::Undo).unwrap();
+        assert_eq!(game.state(), New);
+        assert_eq!(game.time(), 0.0);
+    }
+
+    #[test]
+    fn clock_starts_once_board_is_generated() {
+        let generator = generator::NoGuess::new(0).with_limits(20, Duration::from_secs(5));
+        let mut game = Game::with_generator(
+            Settings {
+                difficulty: Difficulty::Custom(CustomBoard::new(2, 2, 2)),
+                ..Settings::default()
+            },
+            generator,
+        );
+        assert!(game.action(Action::Open(0, 0)).is_err());
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(game.state(), New);
+        assert_eq!(game.time(), 0.0);
+    }
+
+    #[test]
+    fn undo_losing_click() {
+        let mut game = corner_game();
+        game.action(Action::Open(1, 0)).unwrap();
+        assert!(!game.used_undo());
+        game.action(Action::Open(0, 0)).unwrap();
+        assert_eq!(game.state(), Lost);
+        assert!(game.has_finished());
+        assert!(!game.timer.is_running());
+
+        game.action(Action::Undo).unwrap();
+        assert_eq!(game.state(), Playing);
+        assert!(game.has_finished());
+        assert!(game.used_undo());
+        assert_eq!(game.cell(0, 0), Cell::Hidden);
+        assert_eq!(game.cell(1, 0), Cell::Open(1));
+        assert!(game.timer.is_running());
+
+        game.action(Action::Redo).unwrap();
+        assert_eq!(game.state(), Lost);
+        assert_eq!(game.cell(0, 0), Cell::TrippedMine);
+        assert!(!game.timer.is_running());
+
+        game.action(Action::Reset).unwrap();
+        assert!(!game.has_finished());
+        assert!(!game.used_undo());
+        assert_eq!(game.action(Action::Undo), Err(GameError::NothingToUndo));
+    }
+
+    #[test]
+    fn undo_disabled() {
+        let mut game = corner_game();
+        game.action(Action::ChangeSettings(Settings {
+            allow_undo: false,
+            ..game.settings().clone()
+        }))
+        .unwrap();
+        game.action(Action::Open(1, 0)).unwrap();
+        assert_eq!(game.action(Action::Undo), Err(GameError::UndoDisabled));
+        assert_eq!(game.action(Action::Redo), Err(GameError::UndoDisabled));
+    }
+
+    #[test]
+    fn pause() {
+        let mut game = corner_game();
+        assert_eq!(game.action(Action::Pause), Err(GameError::NotPlaying));
+        game.action(Action::Open(1, 0)).unwrap();
+        game.action(Action::Pause).unwrap();
+        assert!(game.is_paused());
+        assert!(!game.timer.is_running());
+        let time = game.time();
+        std::thread::sleep(std::time::Duration::from_micros(1));
+        assert_eq!(game.time(), time);
+
+        for action in [
+            Action::Open(2, 0),
+            Action::Flag(0, 0),
+            Action::OpenOrChord(1, 0),
+            Action::Undo,
+            Action::Hint,
+        ] {
+            assert_eq!(game.action(action), Err(GameError::Paused));
+        }
+        assert_eq!(game.clicks(), 1);
+        assert_eq!(game.cell(0, 0), Cell::Hidden);
+
+        game.action(Action::Resume).unwrap();
+        assert!(!game.is_paused());
+        assert!(game.timer.is_running());
+        game.action(Action::Flag(0, 0)).unwrap();
+
+        game.action(Action::Pause).unwrap();
+        game.action(Action::Reset).unwrap();
+        assert!(!game.is_paused());
+    }
+
+    #[test]
+    fn chord_trips_mine() {
+        let mut game = corner_game();
+        game.action(Action::Open(1, 0)).unwrap();
+        game.action(Action::Flag(0, 1)).unwrap();
+        assert_eq!(
+            game.action(Action::Chord(1, 0)),
+            Err(GameError::TrippedMine { x: 0, y: 0 })
+        );
+        assert_eq!(game.state(), Lost);
+        assert_eq!(game.cell(0, 1), Cell::FalseFlag);
+        assert_eq!(game.action(Action::Open(2, 0)), Err(GameError::GameOver));
+    }
+}
@@ -1,10 +1,71 @@
-use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::{
     cmp::{max, min},
-    fmt::Display,
+    sync::Arc,
 };
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+use super::{
+    generator::{BoardGenerator, UniformRandom},
+    BoardError, GameError,
+};
+
+pub const MIN_SIDE: usize = 1;
+pub const MIN_MINES: usize = 1;
+pub const MAX_AREA: usize = 99 * 99;
+
+pub fn validate(width: usize, height: usize, num_mines: usize) -> Result<(), BoardError> {
+    let area = width.checked_mul(height);
+    if width < MIN_SIDE {
+        Err(BoardError::WidthTooSmall {
+            width,
+            min: MIN_SIDE,
+        })
+    } else if height < MIN_SIDE {
+        Err(BoardError::HeightTooSmall {
+            height,
+            min: MIN_SIDE,
+        })
+    } else if area.is_none_or(|area| area > MAX_AREA) {
+        Err(BoardError::AreaTooLarge {
+            area: width.saturating_mul(height),
+            max: MAX_AREA,
+        })
+    } else if num_mines < MIN_MINES {
+        Err(BoardError::TooFewMines {
+            mines: num_mines,
+            min: MIN_MINES,
+        })
+    } else if num_mines > width * height - 1 {
+        Err(BoardError::TooManyMines {
+            mines: num_mines,
+            max: width * height - 1,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+pub fn check_layout(
+    mines: &[Vec<bool>],
+    width: usize,
+    height: usize,
+    num_mines: usize,
+) -> Result<(), BoardError> {
+    if mines.len() != height
+        || mines.iter().any(|row| row.len() != width)
+        || mines.iter().flatten().filter(|&&mine| mine).count() != num_mines
+    {
+        Err(BoardError::LayoutMismatch {
+            width,
+            height,
+            mines: num_mines,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameState {
     New,
     Playing,
@@ -14,10 +75,7 @@ pub enum GameState {
 
 impl GameState {
     pub fn has_ended(&self) -> bool {
-        match self {
-            GameState::Won | GameState::Lost => true,
-            _ => false,
-        }
+        matches!(self, GameState::Won | GameState::Lost)
     }
 }
 
@@ -30,10 +88,7 @@ pub enum Cell {
 
 impl Cell {
     pub fn is_clear(&self) -> bool {
-        match self {
-            Cell::Clear(_) => true,
-            _ => false,
-        }
+        matches!(self, Cell::Clear(_))
     }
 }
 
@@ -43,78 +98,73 @@ pub struct OpenInfo {
     pub cell: Cell,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum OpenErr {
-    OutOfBounds,
-    AlreadyOpen,
-    GameOver,
-}
-
-impl std::error::Error for OpenErr {}
-
-impl Display for OpenErr {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            OpenErr::OutOfBounds => write!(f, "Out of bounds"),
-            OpenErr::AlreadyOpen => write!(f, "Already open"),
-            OpenErr::GameOver => write!(f, "Game over"),
-        }
-    }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct GameRules {
     state: GameState,
     width: usize,
     height: usize,
     num_mines: usize,
-    randomizer: fn(usize, usize, usize, usize, usize) -> Vec<Vec<bool>>,
+    generator: Arc<dyn BoardGenerator>,
     clear_remaining: usize,
     mines: Vec<Vec<bool>>,
     opened: Vec<Vec<bool>>,
     neighbours: Vec<Vec<u8>>,
 }
 
+impl PartialEq for GameRules {
+    fn eq(&self, other: &Self) -> bool {
+        self.state == other.state
+            && self.width == other.width
+            && self.height == other.height
+            && self.num_mines == other.num_mines
+            && Arc::ptr_eq(&self.generator, &other.generator)
+            && self.clear_remaining == other.clear_remaining
+            && self.mines == other.mines
+            && self.opened == other.opened
+            && self.neighbours == other.neighbours
+    }
+}
+
+impl Eq for GameRules {}
+
 impl GameRules {
     pub fn new(width: usize, height: usize, num_mines: usize) -> Self {
-        Self::new_with(height, width, num_mines, default_randomizer)
+        Self::try_new(width, height, num_mines).unwrap_or_else(|err| panic!("{}", err))
     }
 
+    #[cfg(test)]
     pub fn new_with(
         width: usize,
         height: usize,
         num_mines: usize,
-        randomizer: fn(usize, usize, usize, usize, usize) -> Vec<Vec<bool>>,
+        generator: impl BoardGenerator + 'static,
     ) -> Self {
-        if width < 1 || height < 1 {
-            panic!("Invalid size");
-        }
-        if num_mines < 1 || num_mines > (width * height - 1) {
-            panic!("Invalid number of mines");
-        }
-        Self {
+        Self::try_new_with(width, height, num_mines, Arc::new(generator))
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_new(width: usize, height: usize, num_mines: usize) -> Result<Self, BoardError> {
+        Self::try_new_with(width, height, num_mines, Arc::new(UniformRandom))
+    }
+
+    pub fn try_new_with(
+        width: usize,
+        height: usize,
+        num_mines: usize,
+        generator: Arc<dyn BoardGenerator>,
+    ) -> Result<Self, BoardError> {
+        validate(width, height, num_mines)?;
+        Ok(Self {
             state: GameState::New,
             width,
             height,
             num_mines,
-            randomizer,
+            generator,
             clear_remaining: 0,
             mines: Vec::new(),
             opened: Vec::new(),
             neighbours: Vec::new(),
-        }
-    }
-
-    pub fn width(&self) -> usize {
-        self.width
-    }
-
-    pub fn height(&self) -> usize {
-        self.height
-    }
-
-    pub fn num_mines(&self) -> usize {
-        self.num_mines
+        })
     }
 
     pub fn clear(&mut self) {
@@ -131,11 +181,59 @@ impl GameRules {
         self.clear();
     }
 
-    pub fn reset_randomizer(
+    /// Sets the generator used for the next board.
+    pub fn set_generator(&mut self, generator: Arc<dyn BoardGenerator>) {
+        self.generator = generator;
+    }
+
+    pub fn first_click_safe(&self) -> bool {
+        self.generator.first_click_safe()
+    }
+
+    pub fn layout(&self) -> Option<&Vec<Vec<bool>>> {
+        match self.state {
+            GameState::New => None,
+            _ => Some(&self.mines),
+        }
+    }
+
+    pub fn opened(&self) -> Option<&Vec<Vec<bool>>> {
+        match self.state {
+            GameState::New => None,
+            _ => Some(&self.opened),
+        }
+    }
+
+    pub fn restore(
         &mut self,
-        randomizer: fn(usize, usize, usize, usize, usize) -> Vec<Vec<bool>>,
-    ) {
-        self.randomizer = randomizer;
+        mines: Vec<Vec<bool>>,
+        opened: Vec<Vec<bool>>,
+    ) -> Result<(), BoardError> {
+        check_layout(&mines, self.width, self.height, self.num_mines)?;
+        if opened.len() != self.height || opened.iter().any(|row| row.len() != self.width) {
+            return Err(self.layout_mismatch());
+        }
+        let mut tripped = false;
+        let mut opened_clear = 0;
+        for (mine, open) in mines.iter().flatten().zip(opened.iter().flatten()) {
+            match (mine, open) {
+                (true, true) => tripped = true,
+                (false, true) => opened_clear += 1,
+                _ => {}
+            }
+        }
+        self.mines = mines;
+        self.opened = opened;
+        self.calculate_neighbours();
+        self.clear_remaining = self.width * self.height - self.num_mines - opened_clear;
+        self.state = if tripped {
+            GameState::Lost
+        } else if self.clear_remaining == 0 {
+            GameState::Won
+        } else {
+            GameState::Playing
+        };
+        Ok(())
     }
 
     pub fn get_state(&self) -> GameState {
@@ -156,25 +254,26 @@ impl GameRules {
         }
     }
 
+    #[cfg(test)]
     pub fn get_board(&self) -> Vec<Vec<Cell>> {
         (0..self.height)
             .map(|y| (0..self.width).map(|x| self.get_cell(x, y)).collect())
             .collect()
     }
 
-    pub fn open(&mut self, x: usize, y: usize) -> Result<OpenInfo, OpenErr> {
+    pub fn open(&mut self, x: usize, y: usize) -> Result<OpenInfo, GameError> {
         if x >= self.width || y >= self.height {
-            return Err(OpenErr::OutOfBounds);
+            return Err(GameError::OutOfBounds { x, y });
         }
         match self.state {
             GameState::New => {
+                self.generate_board(x, y)?;
                 self.state = GameState::Playing;
-                self.generate_board(x, y);
                 self.open(x, y)
             }
             GameState::Playing => {
                 if self.opened[y][x] {
-                    Err(OpenErr::AlreadyOpen)
+                    Err(GameError::AlreadyOpen { x, y })
                 } else {
                     self.opened[y][x] = true;
                     if self.mines[y][x] {
@@ -195,17 +294,30 @@ impl GameRules {
                     }
                 }
             }
-            _ => Err(OpenErr::GameOver),
+            _ => Err(GameError::GameOver),
         }
     }
 
     // Utility functions
 
-    fn generate_board(&mut self, firstx: usize, firsty: usize) {
-        self.mines = (self.randomizer)(self.width, self.height, self.num_mines, firstx, firsty);
+    fn layout_mismatch(&self) -> BoardError {
+        BoardError::LayoutMismatch {
+            width: self.width,
+            height: self.height,
+            mines: self.num_mines,
+        }
+    }
+
+    fn generate_board(&mut self, firstx: usize, firsty: usize) -> Result<(), GameError> {
+        let mines =
+            self.generator
+                .generate(self.width, self.height, self.num_mines, (firstx, firsty))?;
+        check_layout(&mines, self.width, self.height, self.num_mines)?;
+        self.mines = mines;
         self.opened = vec![vec![false; self.width]; self.height];
         self.calculate_neighbours();
         self.clear_remaining = self.width * self.height - self.num_mines;
+        Ok(())
     }
 
     fn calculate_neighbours(&mut self) {
@@ -224,43 +336,10 @@ impl GameRules {
     }
 }
 
-fn default_randomizer(
-    width: usize,
-    height: usize,
-    num_mines: usize,
-    firstx: usize,
-    firsty: usize,
-) -> Vec<Vec<bool>> {
-    let mut mines = vec![vec![false; width]; height];
-    let mut mines_left = num_mines;
-    let area = width * height;
-    let spaces = area - num_mines - 1;
-    mines[firsty][firstx] = true;
-
-    let mut rng = rand::thread_rng();
-    while mines_left > 0 {
-        let r = rng.gen_range(0..spaces + mines_left);
-        let mut i = 0;
-        for j in 0..area {
-            let (x, y) = (j % width, j / width);
-            if mines[y][x] {
-                continue;
-            }
-            if i == r {
-                mines[y][x] = true;
-                mines_left -= 1;
-                break;
-            }
-            i += 1;
-        }
-    }
-    mines[firsty][firstx] = false;
-    mines
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::game::generator::SeededRandom;
 
     fn dummy_randomizer(
         width: usize,
@@ -297,9 +376,9 @@ mod tests {
     #[test]
     fn run() {
         let mut game = GameRules::new_with(5, 4, 10, dummy_randomizer);
-        assert_eq!(game.height(), 4);
-        assert_eq!(game.width(), 5);
-        assert_eq!(game.num_mines(), 10);
+        assert_eq!(game.height, 4);
+        assert_eq!(game.width, 5);
+        assert_eq!(game.num_mines, 10);
         assert_eq!(game.get_state(), GameState::New);
         assert_eq!(
             game.open(0, 2),
@@ -311,9 +390,9 @@ mod tests {
         assert_eq!(game.get_state(), GameState::Playing);
         assert_eq!(game.get_cell(0, 2), Cell::Clear(2));
         assert_eq!(game.get_cell(0, 3), Cell::Hidden);
-        assert_eq!(game.open(0, 2), Err(OpenErr::AlreadyOpen));
-        assert_eq!(game.open(4, 4), Err(OpenErr::OutOfBounds));
-        assert_eq!(game.open(5, 3), Err(OpenErr::OutOfBounds));
+        assert_eq!(game.open(0, 2), Err(GameError::AlreadyOpen { x: 0, y: 2 }));
+        assert_eq!(game.open(4, 4), Err(GameError::OutOfBounds { x: 4, y: 4 }));
+        assert_eq!(game.open(5, 3), Err(GameError::OutOfBounds { x: 5, y: 3 }));
         assert_eq!(
             game.open(1, 1),
             Ok(OpenInfo {
@@ -322,7 +401,7 @@ mod tests {
             })
         );
         assert_eq!(game.get_state(), GameState::Lost);
-        assert_eq!(game.open(1, 2), Err(OpenErr::GameOver));
+        assert_eq!(game.open(1, 2), Err(GameError::GameOver));
         assert_eq!(game.get_cell(0, 0), Cell::Mine);
         assert_eq!(game.get_cell(3, 3), Cell::Clear(0));
 
@@ -336,7 +415,7 @@ mod tests {
                 cell: Cell::Clear(0)
             })
         );
-        assert_eq!(game.open(0, 1), Err(OpenErr::OutOfBounds));
+        assert_eq!(game.open(0, 1), Err(GameError::OutOfBounds { x: 0, y: 1 }));
         assert_eq!(
             game.open(1, 0),
             Ok(OpenInfo {
@@ -346,7 +425,7 @@ mod tests {
         );
 
         assert_eq!(game.get_state(), GameState::Won);
-        assert_eq!(game.open(1, 0), Err(OpenErr::GameOver));
+        assert_eq!(game.open(1, 0), Err(GameError::GameOver));
 
         assert_eq!(
             game.get_board(),
@@ -358,7 +437,7 @@ mod tests {
     }
 
     #[test]
-    fn reset_randomizer() {
+    fn set_generator() {
         let mut game = GameRules::new_with(2, 1, 1, dummy_randomizer);
         assert_eq!(
             game.open(0, 0),
@@ -368,7 +447,7 @@ mod tests {
             })
         );
 
-        game.reset_randomizer(dummy_randomizer_2);
+        game.set_generator(Arc::new(dummy_randomizer_2));
 
         assert_eq!(game.state, GameState::Lost);
         game.clear();
@@ -387,52 +466,66 @@ mod tests {
     }
 
     #[test]
-    #[should_panic]
-    fn invalid_size() {
-        GameRules::new(0, 5, 0);
+    fn game_new_not_square() {
+        let mut game = GameRules::new(5, 4, 10);
+        assert_eq!(game.width, 5);
+        assert_eq!(game.height, 4);
+        assert!(game.open(4, 3).is_ok());
     }
 
     #[test]
     #[should_panic]
-    fn invalid_mines_1() {
-        GameRules::new(3, 3, 9);
-    }
-
-    #[test]
-    #[should_panic]
-    fn invalid_mines_2() {
-        GameRules::new(3, 3, 0);
+    fn invalid_size() {
+        GameRules::new(0, 5, 0);
     }
 
     #[test]
-    fn default_randomizer_basic() {
-        let mines = default_randomizer(5, 4, 10, 0, 0);
+    fn try_new_reports_constraint() {
         assert_eq!(
-            mines
-                .into_iter()
-                .map(|v| v.into_iter().filter(|&x| x).count())
-                .sum::<usize>(),
-            10
+            GameRules::try_new(0, 5, 1).err(),
+            Some(BoardError::WidthTooSmall { width: 0, min: 1 })
         );
+        assert_eq!(
+            GameRules::try_new(5, 0, 1).err(),
+            Some(BoardError::HeightTooSmall { height: 0, min: 1 })
+        );
+        assert_eq!(
+            GameRules::try_new(100, 100, 1).err(),
+            Some(BoardError::AreaTooLarge {
+                area: 10000,
+                max: MAX_AREA
+            })
+        );
+        assert_eq!(
+            GameRules::try_new(3, 3, 0).err(),
+            Some(BoardError::TooFewMines { mines: 0, min: 1 })
+        );
+        assert_eq!(
+            GameRules::try_new(3, 3, 9).err(),
+            Some(BoardError::TooManyMines { mines: 9, max: 8 })
+        );
+        assert!(GameRules::try_new(3, 3, 8).is_ok());
     }
 
     #[test]
-    fn randomizer_does_not_get_first() {
-        for i in 0..5 {
-            let mines = default_randomizer(5, 5, 24, i, i);
-            assert_eq!(mines[i][i], false);
-        }
+    fn seeded_generator_repeats_after_clear() {
+        let mut game = GameRules::new_with(16, 16, 40, SeededRandom::new(7));
+        game.open(3, 4).unwrap();
+        let first = game.layout().cloned();
+        game.clear();
+        game.open(3, 4).unwrap();
+        assert_eq!(game.layout().cloned(), first);
     }
 
     #[test]
     #[should_panic]
-    fn randomizer_zero_size_board() {
-        default_randomizer(0, 0, 0, 0, 0);
+    fn invalid_mines_1() {
+        GameRules::new(3, 3, 9);
     }
 
     #[test]
     #[should_panic]
-    fn randomizer_too_many_mines() {
-        default_randomizer(5, 5, 25, 0, 0);
+    fn invalid_mines_2() {
+        GameRules::new(3, 3, 0);
     }
 }
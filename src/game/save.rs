@@ -0,0 +1,301 @@
+use std::{fmt::Display, fs, io, path::Path, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::{BoardError, Cell, Game, GameState, Settings};
+
+pub const SAVE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveFile {
+    version: u32,
+    settings: Settings,
+    layout: Option<Layout>,
+    elapsed: f64,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    seeded: bool,
+    #[serde(default)]
+    hints_used: usize,
+    #[serde(default)]
+    clicks: usize,
+    #[serde(default)]
+    used_flags: bool,
+    #[serde(default)]
+    used_undo: bool,
+    #[serde(default)]
+    finished: bool,
+    #[serde(default)]
+    paused: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Layout {
+    mines: Vec<Vec<bool>>,
+    opened: Vec<Vec<bool>>,
+    flags: Vec<Vec<bool>>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    UnsupportedVersion(u32),
+    InvalidBoard(BoardError),
+}
+
+impl std::error::Error for SaveError {}
+
+impl Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "{}", err),
+            SaveError::Format(err) => write!(f, "malformed file: {}", err),
+            SaveError::UnsupportedVersion(version) => {
+                write!(f, "file format version {} is not supported", version)
+            }
+            SaveError::InvalidBoard(err) => write!(f, "invalid saved board: {}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SaveError {
+    fn from(err: io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for SaveError {
+    fn from(err: serde_json::Error) -> Self {
+        SaveError::Format(err)
+    }
+}
+
+impl From<BoardError> for SaveError {
+    fn from(err: BoardError) -> Self {
+        SaveError::InvalidBoard(err)
+    }
+}
+
+impl Game {
+    /// Writes the game to `path`, creating parent directories as needed. The undo history
+    /// is not saved.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let layout = match (self.rules.layout(), self.rules.opened()) {
+            (Some(mines), Some(opened)) => Some(Layout {
+                mines: mines.clone(),
+                opened: opened.clone(),
+                flags: self
+                    .board
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|cell| matches!(cell, Cell::Flag | Cell::FalseFlag))
+                            .collect()
+                    })
+                    .collect(),
+            }),
+            _ => None,
+        };
+        let save = SaveFile {
+            version: SAVE_VERSION,
+            settings: self.settings.clone(),
+            layout,
+            elapsed: self.time(),
+            seed: self.seed(),
+            seeded: self.seeded,
+            hints_used: self.hints_used,
+            clicks: self.clicks,
+            used_flags: self.used_flags,
+            used_undo: self.used_undo,
+            finished: self.finished,
+            paused: self.paused,
+        };
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(&save)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Game, SaveError> {
+        let contents = fs::read_to_string(path)?;
+        let header: SaveHeader = serde_json::from_str(&contents)?;
+        if header.version != SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(header.version));
+        }
+        let save: SaveFile = serde_json::from_str(&contents)?;
+
+        let mut game = Game::new(save.settings);
+        if let Some(seed) = save.seed {
+            game.set_seed(seed);
+        }
+        game.seeded |= save.seeded;
+        game.hints_used = save.hints_used;
+        game.clicks = save.clicks;
+        game.used_flags = save.used_flags;
+        game.used_undo = save.used_undo;
+        game.finished = save.finished;
+        if let Some(layout) = save.layout {
+            let Layout {
+                mines,
+                opened,
+                flags,
+            } = layout;
+            if flags.len() != game.height() || flags.iter().any(|row| row.len() != game.width()) {
+                return Err(BoardError::LayoutMismatch {
+                    width: game.width(),
+                    height: game.height(),
+                    mines: game.mines(),
+                }
+                .into());
+            }
+            game.rules.restore(mines.clone(), opened.clone())?;
+            for (y, row) in flags.iter().enumerate() {
+                for (x, &flag) in row.iter().enumerate() {
+                    game.board[y][x] = if flag {
+                        game.mines_remaining = game.mines_remaining.saturating_sub(1);
+                        Cell::Flag
+                    } else if !opened[y][x] {
+                        Cell::Hidden
+                    } else if mines[y][x] {
+                        Cell::TrippedMine
+                    } else {
+                        game.rules.get_cell(x, y).into()
+                    };
+                }
+            }
+            if game.state().has_ended() {
+                game.query_board();
+            }
+            game.timer
+                .add_time(Duration::from_secs_f64(save.elapsed.max(0.0)));
+            if game.state() == GameState::Playing {
+                if save.paused {
+                    game.paused = true;
+                } else {
+                    game.timer.resume();
+                }
+            }
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{rules::GameRules, Action, CustomBoard, Difficulty, GameState};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("minesweeper-{}-{}.json", name, std::process::id()))
+    }
+
+    fn corner_mines(
+        width: usize,
+        height: usize,
+        _num_mines: usize,
+        _firstx: usize,
+        _firsty: usize,
+    ) -> Vec<Vec<bool>> {
+        let mut mines = vec![vec![false; width]; height];
+        mines[0][0] = true;
+        mines[height - 1][width - 1] = true;
+        mines
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut game = Game::new(Settings {
+            difficulty: Difficulty::Custom(CustomBoard::new(4, 3, 2)),
+            ..Settings::default()
+        });
+        game.rules = GameRules::new_with(4, 3, 2, corner_mines);
+        game.action(Action::Open(3, 0)).unwrap();
+        game.action(Action::Flag(0, 0)).unwrap();
+        game.action(Action::Hint).unwrap();
+
+        let path = temp_path("round-trip");
+        let elapsed = game.time();
+        game.save(&path).unwrap();
+        let loaded = Game::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.settings(), game.settings());
+        assert_eq!(loaded.board(), game.board());
+        assert_eq!(loaded.mines_remaining(), 1);
+        assert_eq!(loaded.state(), GameState::Playing);
+        assert!(loaded.time() >= elapsed);
+        assert_eq!(loaded.rules.layout(), game.rules.layout());
+        assert_eq!(loaded.seed(), game.seed());
+        assert_eq!(loaded.hints_used(), 1);
+        assert_eq!(loaded.clicks(), 2);
+        assert!(loaded.used_flags());
+        assert!(!loaded.is_paused());
+    }
+
+    #[test]
+    fn seed() {
+        let mut game = Game::new(Settings::default());
+        let path = temp_path("seed");
+        game.save(&path).unwrap();
+        let mut loaded = Game::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.seed(), game.seed());
+        game.action(Action::Open(4, 4)).unwrap();
+        loaded.action(Action::Open(4, 4)).unwrap();
+        assert_eq!(loaded.rules.layout(), game.rules.layout());
+    }
+
+    #[test]
+    fn seeded() {
+        let mut game = Game::new(Settings {
+            seed: Some(3),
+            ..Settings::default()
+        });
+        // Clearing the seed keeps the board it gave.
+        game.action(Action::ChangeSettings(Settings::default()))
+            .unwrap();
+        let path = temp_path("seeded");
+        game.save(&path).unwrap();
+        let loaded = Game::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.board_is_chosen());
+    }
+
+    #[test]
+    fn paused() {
+        let mut game = Game::new(Settings {
+            difficulty: Difficulty::Custom(CustomBoard::new(4, 3, 2)),
+            ..Settings::default()
+        });
+        game.rules = GameRules::new_with(4, 3, 2, corner_mines);
+        game.action(Action::Open(3, 0)).unwrap();
+        game.action(Action::Pause).unwrap();
+
+        let path = temp_path("paused");
+        game.save(&path).unwrap();
+        let mut loaded = Game::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.is_paused());
+        assert!((loaded.time() - game.time()).abs() < 1e-6);
+        loaded.action(Action::Resume).unwrap();
+        assert!(loaded.time() >= game.time());
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let path = temp_path("version");
+        fs::write(&path, r#"{"version": 999}"#).unwrap();
+        let result = Game::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(SaveError::UnsupportedVersion(999))));
+    }
+}
@@ -1,17 +1,101 @@
+pub mod analysis;
+pub mod bot;
+pub mod config;
 mod game;
+pub mod records;
+pub mod replay;
+pub mod sim;
+pub mod solver;
+pub mod stats;
+pub mod storage;
 mod ui;
 
-pub use game::{Action, Cell, CustomBoard, Difficulty, Game, Settings};
-pub use ui::{CLUI, TUI};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use config::Config;
+pub use game::{
+    generator, Action, BoardError, BoardGenerator, Cell, CustomBoard, Difficulty, Game, GameError,
+    GameState, GeneratorKind, Hint, ParseDifficultyError, SaveError, Settings,
+};
+use records::{Leaderboard, Record};
+use replay::{Recorder, Replay};
+use stats::{ExportFormat, Stats};
+pub use ui::{BotUI, Keymap, KeymapError, Theme, CLUI, TUI};
+
+/// The longest pause between two actions when playing back a replay.
+const MAX_REPLAY_WAIT: f64 = 5.0;
 
 pub trait UI {
     fn get_action(&mut self, game: &Game) -> Action;
     fn show_msg(&mut self, msg: &str);
+
+    fn show_error(&mut self, err: &GameError) {
+        self.show_msg(&err.to_string());
+    }
+
+    /// Asks the player a yes or no question. UIs that can't ask say no.
+    fn confirm(&mut self, prompt: &str) -> bool {
+        let _ = prompt;
+        false
+    }
+
+    /// Shows the lifetime stats for every difficulty played. Returns a format if the player
+    /// asks to export them.
+    fn show_stats(&mut self, stats: &Stats) -> Option<ExportFormat> {
+        let mut msg = String::from("Won/played:");
+        for (difficulty, tally) in stats.iter() {
+            msg.push_str(&format!(" {} {}/{}", difficulty, tally.won, tally.played));
+        }
+        self.show_msg(&msg);
+        None
+    }
+
+    /// Shows the best times on `difficulty`, fastest first.
+    fn show_records(&mut self, difficulty: &Difficulty, records: &[Record]) {
+        let mut msg = format!("Best times on {}:", difficulty);
+        if records.is_empty() {
+            msg.push_str(" none yet");
+        }
+        for (i, record) in records.iter().enumerate() {
+            msg.push_str(&format!(" {}. {:.2}s", i + 1, record.time));
+        }
+        self.show_msg(&msg);
+    }
+
+    /// Shows `msg` while the engine does something slow, such as generating a board, before
+    /// the next call to `get_action`.
+    fn show_busy(&mut self, game: &Game, msg: &str) {
+        let _ = (game, msg);
+    }
+
+    /// The name of the theme in use, for UIs that have themes, so it can be kept in the
+    /// config.
+    fn theme(&self) -> Option<&str> {
+        None
+    }
+
+    /// Shows `game` for `wait` while something other than the player picks the actions,
+    /// such as a [`BotUI`]. Returns an action if the player interrupts, such as to quit.
+    fn spectate(&mut self, game: &Game, wait: Duration) -> Option<Action> {
+        let _ = game;
+        thread::sleep(wait);
+        None
+    }
 }
 
 pub struct Engine {
     game: game::Game,
     ui: Box<dyn UI>,
+    autosave: Option<PathBuf>,
+    recorder: Option<Recorder>,
+    records: Option<PathBuf>,
+    stats: Option<PathBuf>,
+    config: Option<(PathBuf, Config)>,
 }
 
 impl Engine {
@@ -19,19 +103,432 @@ impl Engine {
         Engine {
             game: game::Game::default(),
             ui: frontend,
+            autosave: None,
+            recorder: None,
+            records: None,
+            stats: None,
+            config: None,
         }
     }
 
-    pub fn run(&mut self) {
+    /// Starts with `game` instead of a new game on the default settings, such as one loaded
+    /// from a save file.
+    pub fn with_game(mut self, game: Game) -> Self {
+        self.game = game;
+        self
+    }
+
+    /// Records every game and writes a replay file for it to `dir` when it ends.
+    pub fn with_replays(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.recorder = Some(Recorder::new(dir.into(), &self.game));
+        self
+    }
+
+    /// Saves an unfinished game to `path` on quit, and offers to resume it on the next run.
+    pub fn with_autosave(mut self, path: impl Into<PathBuf>) -> Self {
+        self.autosave = Some(path.into());
+        self
+    }
+
+    /// Keeps the best times in a leaderboard at `path`. Wins with hints don't count.
+    pub fn with_records(mut self, path: impl Into<PathBuf>) -> Self {
+        self.records = Some(path.into());
+        self
+    }
+
+    /// Keeps lifetime stats for each difficulty in `path`. Exports of them are written to the
+    /// current directory.
+    pub fn with_stats(mut self, path: impl Into<PathBuf>) -> Self {
+        self.stats = Some(path.into());
+        self
+    }
+
+    /// Writes the settings back to `config` at `path` whenever they are changed.
+    pub fn with_config(mut self, path: impl Into<PathBuf>, config: Config) -> Self {
+        self.config = Some((path.into(), config));
+        self
+    }
+
+    pub fn run(&mut self) -> Result<(), SaveError> {
+        self.offer_resume();
         loop {
             match self.ui.get_action(&self.game) {
                 Action::Quit => break,
-                action => self
-                    .game
-                    .action(action)
-                    .err()
-                    .map(|err| self.ui.show_msg(err)),
+                Action::ShowRecords => self.show_records(),
+                Action::ShowStats => self.show_stats(),
+                action => {
+                    let was_over = self.game.state().has_ended();
+                    let was_finished = self.game.has_finished();
+                    let changes_settings = matches!(action, Action::ChangeSettings(_));
+                    let new_game = self.starts_new_game(&action);
+                    if new_game && self.game.state() == GameState::Playing {
+                        self.write_replay();
+                    }
+                    if self.game.generates_board(&action) {
+                        self.ui.show_busy(&self.game, "Generating board...");
+                    }
+                    match self.game.action(action.clone()) {
+                        Err(err) => self.ui.show_error(&err),
+                        Ok(()) => {
+                            self.record(&action, new_game);
+                            if changes_settings {
+                                self.save_config();
+                            }
+                        }
+                    }
+                    if !was_over && self.game.state().has_ended() {
+                        self.write_replay();
+                        // Finishing again after undoing past the end is still the same game,
+                        // so it isn't counted again.
+                        let record = if was_finished {
+                            None
+                        } else {
+                            self.add_record()
+                        };
+                        let msg = match record {
+                            Some(record) => format!("{}. {}", record, summary(&self.game)),
+                            None => summary(&self.game),
+                        };
+                        self.ui.show_msg(&msg);
+                        if !was_finished {
+                            self.add_stats();
+                        }
+                    }
+                }
             };
         }
+        if let Some(recorder) = &mut self.recorder {
+            if self.game.state() == GameState::Playing {
+                recorder.write(&self.game)?;
+            }
+        }
+        self.autosave()
+    }
+
+    /// Whether `action` replaces the game with a new one, which gets a replay of its own.
+    fn starts_new_game(&self, action: &Action) -> bool {
+        match action {
+            Action::Reset => true,
+            Action::ChangeSettings(settings) => self.game.would_reset(settings),
+            _ => false,
+        }
+    }
+
+    /// Adds an action the game accepted to the replay.
+    fn record(&mut self, action: &Action, new_game: bool) {
+        if let Some(recorder) = &mut self.recorder {
+            if new_game {
+                recorder.restart();
+            } else {
+                recorder.record(&self.game, action);
+            }
+        }
+    }
+
+    fn write_replay(&mut self) {
+        if let Some(recorder) = &self.recorder {
+            if let Err(err) = recorder.write(&self.game) {
+                self.ui
+                    .show_msg(&format!("Could not write replay: {}", err));
+            }
+        }
+    }
+
+    /// Adds a won game to the leaderboard, returning a line about its place if it made it.
+    fn add_record(&self) -> Option<String> {
+        let path = self.records.as_ref()?;
+        let record = Record::new(&self.game)?;
+        let difficulty = self.game.settings().difficulty;
+        let result = Leaderboard::load(path).and_then(|mut leaderboard| {
+            let place = leaderboard.add(&difficulty, record);
+            if place.is_some() {
+                leaderboard.save(path)?;
+            }
+            Ok(place)
+        });
+        match result {
+            Ok(Some(0)) => Some(format!("New record on {}", difficulty)),
+            Ok(Some(place)) => Some(format!("#{} on the {} leaderboard", place + 1, difficulty)),
+            Ok(None) => None,
+            Err(err) => Some(format!("Could not save the record: {}", err)),
+        }
+    }
+
+    fn show_records(&mut self) {
+        let path = match &self.records {
+            Some(path) => path,
+            None => return self.ui.show_msg("Records are not being kept"),
+        };
+        let difficulty = self.game.settings().difficulty;
+        match Leaderboard::load(path) {
+            Ok(leaderboard) => self
+                .ui
+                .show_records(&difficulty, leaderboard.records(&difficulty)),
+            Err(err) => self
+                .ui
+                .show_msg(&format!("Could not load the records: {}", err)),
+        }
+    }
+
+    fn save_config(&mut self) {
+        if let Some((path, config)) = &mut self.config {
+            config.set_settings(self.game.settings());
+            if let Some(theme) = self.ui.theme() {
+                config.theme = theme.to_string();
+            }
+            if let Err(err) = config.save(path) {
+                self.ui
+                    .show_msg(&format!("Could not save the config: {}", err));
+            }
+        }
+    }
+
+    fn add_stats(&mut self) {
+        let path = match &self.stats {
+            Some(path) => path,
+            None => return,
+        };
+        let result = Stats::load(path).and_then(|mut stats| {
+            stats.add(&self.game);
+            stats.save(path)
+        });
+        if let Err(err) = result {
+            self.ui
+                .show_msg(&format!("Could not save the stats: {}", err));
+        }
+    }
+
+    fn show_stats(&mut self) {
+        let path = match &self.stats {
+            Some(path) => path.clone(),
+            None => return self.ui.show_msg("Stats are not being kept"),
+        };
+        let stats = match Stats::load(&path) {
+            Ok(stats) => stats,
+            Err(err) => {
+                return self
+                    .ui
+                    .show_msg(&format!("Could not load the stats: {}", err))
+            }
+        };
+        if let Some(format) = self.ui.show_stats(&stats) {
+            let export = PathBuf::from(format!("minesweeper-stats.{}", format.extension()));
+            match stats.export(&export, format) {
+                Ok(()) => self
+                    .ui
+                    .show_msg(&format!("Stats exported to {}", export.display())),
+                Err(err) => self
+                    .ui
+                    .show_msg(&format!("Could not export the stats: {}", err)),
+            }
+        }
+    }
+
+    /// Plays back `replay` at the pace it was recorded, then shows the final board until the
+    /// player quits.
+    pub fn play_replay(&mut self, replay: &Replay) -> Result<(), BoardError> {
+        let mut player = replay.player()?;
+        let mut last = 0.0;
+        while let Some(event) = player.peek() {
+            let wait = Duration::from_secs_f64((event.time - last).clamp(0.0, MAX_REPLAY_WAIT));
+            if self.ui.spectate(player.game(), wait) == Some(Action::Quit) {
+                return Ok(());
+            }
+            last = event.time;
+            player.step();
+        }
+        let game = player.finish();
+        let result = match replay.result {
+            GameState::Won => "won",
+            GameState::Lost => "lost",
+            _ => "unfinished",
+        };
+        self.ui.show_msg(&format!(
+            "End of replay: {} in {:.2}s. Press q to quit",
+            result, replay.time
+        ));
+        while self.ui.get_action(&game) != Action::Quit {}
+        Ok(())
+    }
+
+    /// Offers to resume the autosaved game, unless a game has already been started.
+    fn offer_resume(&mut self) {
+        let path = match &self.autosave {
+            Some(path) if path.exists() && self.game.state() == GameState::New => path.clone(),
+            _ => return,
+        };
+        match Game::load(&path) {
+            Ok(game) => {
+                if game.state() == GameState::Playing && self.ui.confirm("Resume the saved game?") {
+                    self.game = game;
+                } else {
+                    remove_file(&path).ok();
+                }
+            }
+            Err(err) => self
+                .ui
+                .show_msg(&format!("Could not load saved game: {}", err)),
+        }
+    }
+
+    fn autosave(&self) -> Result<(), SaveError> {
+        let path = match &self.autosave {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if self.game.state() == GameState::Playing {
+            self.game.save(path)
+        } else {
+            Ok(remove_file(path)?)
+        }
+    }
+}
+
+/// A line describing how a finished game went.
+fn summary(game: &Game) -> String {
+    let result = match game.state() {
+        GameState::Won => "Won",
+        _ => "Lost",
+    };
+    let hints = match game.hints_used() {
+        0 => "no hints".to_string(),
+        1 => "1 hint".to_string(),
+        n => format!("{} hints", n),
+    };
+    let summary = format!("{} in {:.2}s with {}", result, game.time(), hints);
+    match game.stats() {
+        Some(stats) => format!("{}; {}", summary, stats),
+        None => summary,
+    }
+}
+
+fn remove_file(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+
+    /// Plays a fixed list of actions, then quits.
+    struct ScriptedUI {
+        actions: VecDeque<Action>,
+    }
+
+    impl UI for ScriptedUI {
+        fn get_action(&mut self, _game: &Game) -> Action {
+            // Replays are named after the millisecond their game started, so keep separate
+            // games from sharing one.
+            std::thread::sleep(Duration::from_millis(2));
+            self.actions.pop_front().unwrap_or(Action::Quit)
+        }
+
+        fn show_msg(&mut self, _msg: &str) {}
+    }
+
+    /// An engine that plays `actions` on a 4x3 board with mines in the top left and bottom
+    /// right corners.
+    fn engine(actions: Vec<Action>) -> Engine {
+        let settings = Settings {
+            difficulty: Difficulty::Custom(CustomBoard::new(4, 3, 2)),
+            ..Settings::default()
+        };
+        let mut mines = vec![vec![false; 4]; 3];
+        mines[0][0] = true;
+        mines[2][3] = true;
+        let ui = ScriptedUI {
+            actions: actions.into(),
+        };
+        Engine::new(Box::new(ui)).with_game(Game::with_layout(settings, mines).unwrap())
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("minesweeper-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn replays_leave_out_refused_actions() {
+        let dir = temp_path("engine-replays");
+        let actions = vec![
+            Action::Open(3, 0),
+            Action::Open(3, 0),
+            Action::Flag(0, 0),
+            Action::Open(0, 0),
+            Action::Open(0, 2),
+            Action::Open(1, 1),
+        ];
+        engine(actions).with_replays(&dir).run().unwrap();
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        let replay = Replay::load(files[0].as_ref().unwrap().path());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(files.len(), 1);
+        let replay = replay.unwrap();
+        assert_eq!(replay.result, GameState::Won);
+        let actions: Vec<_> = replay
+            .events
+            .into_iter()
+            .map(|event| event.action)
+            .collect();
+        assert_eq!(
+            actions,
+            [Action::Open(3, 0), Action::Flag(0, 0), Action::Open(0, 2),]
+        );
+    }
+
+    #[test]
+    fn changing_the_seed_starts_a_new_replay() {
+        let dir = temp_path("engine-seed-replays");
+        let settings = Settings {
+            difficulty: Difficulty::Custom(CustomBoard::new(4, 3, 2)),
+            seed: Some(1),
+            ..Settings::default()
+        };
+        let actions = vec![
+            Action::Open(3, 0),
+            Action::ChangeSettings(settings),
+            Action::Open(3, 0),
+            Action::Open(0, 2),
+        ];
+        engine(actions).with_replays(&dir).run().unwrap();
+        let replays: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|file| Replay::load(file.unwrap().path()))
+            .collect();
+        fs::remove_dir_all(&dir).unwrap();
+        let mut lengths: Vec<_> = replays
+            .into_iter()
+            .map(|replay| replay.unwrap().events.len())
+            .collect();
+        lengths.sort();
+        assert_eq!(lengths, [1, 2]);
+    }
+
+    #[test]
+    fn stats_count_each_game_once() {
+        let path = temp_path("engine-stats.json");
+        let actions = vec![
+            // Lose, undo and win.
+            Action::Open(3, 0),
+            Action::Open(0, 0),
+            Action::Undo,
+            Action::Open(0, 2),
+            // Lose, undo and lose again.
+            Action::Reset,
+            Action::Open(3, 0),
+            Action::Open(0, 0),
+            Action::Undo,
+            Action::Redo,
+        ];
+        engine(actions).with_stats(&path).run().unwrap();
+        let stats = Stats::load(&path);
+        fs::remove_file(&path).unwrap();
+        let difficulty = Difficulty::Custom(CustomBoard::new(4, 3, 2));
+        let tally = stats.unwrap().get(&difficulty);
+        assert_eq!((tally.played, tally.won, tally.lost), (2, 0, 2));
     }
 }
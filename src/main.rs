@@ -1,7 +1,125 @@
-use minesweeper::{Engine, Settings, TUI};
+use std::path::PathBuf;
+
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
+use minesweeper::{
+    config::Config, replay::Replay, storage, Engine, Game, Keymap, Theme, CLUI, TUI, UI,
+};
+
+/// Play minesweeper in the terminal.
+#[derive(Debug, Parser)]
+struct Args {
+    #[arg(short, long, value_enum, default_value_t = Frontend::Tui)]
+    ui: Frontend,
+    /// easy, medium, hard, WIDTHxHEIGHT:MINES or the name of a preset in the config.
+    #[arg(short, long)]
+    difficulty: Option<String>,
+    /// Generate every board from this seed.
+    #[arg(short, long)]
+    seed: Option<u64>,
+    /// Play back a replay file instead of playing.
+    #[arg(long, conflicts_with_all = ["difficulty", "seed", "resume"])]
+    replay: Option<PathBuf>,
+    /// Continue a game from a save file.
+    #[arg(long, conflicts_with_all = ["difficulty", "seed"])]
+    resume: Option<PathBuf>,
+    /// Write a replay of every game to the data directory.
+    #[arg(long, conflicts_with = "replay")]
+    record: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Frontend {
+    Tui,
+    Cli,
+}
 
 fn main() {
-    let default_settings = Settings::default();
-    let mut engine = Engine::new(Box::new(TUI::new(&default_settings)));
-    engine.run();
+    let args = Args::parse();
+    // Load files before the TUI takes over the terminal, so errors can be printed.
+    let config_path = storage::config_path();
+    let config = match &config_path {
+        Some(path) => Config::load(path).unwrap_or_else(|err| {
+            exit(&format!(
+                "Could not load config {}: {}",
+                path.display(),
+                err
+            ))
+        }),
+        None => Config::default(),
+    };
+    let mut settings = config.settings.clone();
+    if let Some(name) = &args.difficulty {
+        settings.difficulty = config.difficulty(name).unwrap_or_else(|err| {
+            let msg = format!("invalid value '{}' for '--difficulty': {}", name, err);
+            Args::command().error(ErrorKind::InvalidValue, msg).exit()
+        });
+    }
+    settings.seed = args.seed;
+    let replay = args.replay.map(|path| {
+        Replay::load(&path).unwrap_or_else(|err| exit(&format!("Could not load replay: {}", err)))
+    });
+    let replay_dir = args.record.then(|| {
+        storage::replay_dir().unwrap_or_else(|| exit("No data directory to write replays to"))
+    });
+    let game = match args.resume {
+        Some(path) => Game::load(&path)
+            .unwrap_or_else(|err| exit(&format!("Could not load saved game: {}", err))),
+        None => Game::new(settings),
+    };
+    let ui: Box<dyn UI> = match args.ui {
+        Frontend::Tui => {
+            let keymap = Keymap::new(&config.keymap, &config.keys)
+                .unwrap_or_else(|err| exit(&format!("Invalid key bindings in config: {}", err)));
+            let theme = Theme::named(&config.theme).unwrap_or_else(|| {
+                exit(&format!(
+                    "Unknown theme '{}' in config, expected one of {}",
+                    config.theme,
+                    Theme::NAMES.join(", ")
+                ))
+            });
+            Box::new(
+                TUI::new(game.settings())
+                    .with_keymap(keymap)
+                    .with_theme(theme),
+            )
+        }
+        Frontend::Cli => Box::new(CLUI::new()),
+    };
+    let mut engine = Engine::new(ui);
+
+    if let Some(replay) = replay {
+        let result = engine.play_replay(&replay);
+        drop(engine);
+        if let Err(err) = result {
+            exit(&format!("Invalid replay: {}", err));
+        }
+        return;
+    }
+
+    engine = engine.with_game(game);
+    if let Some(path) = config_path {
+        engine = engine.with_config(path, config);
+    }
+    if let Some(dir) = replay_dir {
+        engine = engine.with_replays(dir);
+    }
+    if let Some(path) = storage::autosave_path() {
+        engine = engine.with_autosave(path);
+    }
+    if let Some(path) = storage::records_path() {
+        engine = engine.with_records(path);
+    }
+    if let Some(path) = storage::stats_path() {
+        engine = engine.with_stats(path);
+    }
+    let result = engine.run();
+    drop(engine);
+    if let Err(err) = result {
+        exit(&format!("Could not save the game: {}", err));
+    }
+}
+
+fn exit(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1);
 }
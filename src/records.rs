@@ -0,0 +1,248 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Difficulty, Game, GameState, SaveError};
+
+pub const RECORDS_VERSION: u32 = 1;
+/// How many times are kept for each difficulty.
+pub const MAX_RECORDS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub time: f64,
+    /// Seconds since the Unix epoch when the game was won.
+    pub date: u64,
+    pub bbbv_per_second: f64,
+    /// Whether any flags were placed during the game.
+    pub flags: bool,
+}
+
+impl Record {
+    /// The record for a won game, or `None` if it wasn't won, hints or undo were used, or the
+    /// board could be known in advance, such as from a seed.
+    pub fn new(game: &Game) -> Option<Self> {
+        if game.state() != GameState::Won
+            || game.hints_used() > 0
+            || game.used_undo()
+            || game.board_is_chosen()
+        {
+            return None;
+        }
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Some(Record {
+            time: game.time(),
+            date,
+            bbbv_per_second: game.stats().map_or(0.0, |stats| stats.bbbv_per_second()),
+            flags: game.used_flags(),
+        })
+    }
+
+    /// The date as YYYY-MM-DD, in UTC.
+    pub fn date_string(&self) -> String {
+        // Days to civil date, from Howard Hinnant's date algorithms.
+        let days = (self.date / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// The best times for each difficulty, fastest first. Custom boards are kept apart by size
+/// and number of mines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Leaderboard {
+    version: u32,
+    boards: BTreeMap<String, Vec<Record>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardHeader {
+    version: u32,
+}
+
+impl Default for Leaderboard {
+    fn default() -> Self {
+        Leaderboard {
+            version: RECORDS_VERSION,
+            boards: BTreeMap::new(),
+        }
+    }
+}
+
+impl Leaderboard {
+    /// Reads the leaderboard at `path`, or an empty one if there is no file yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+        let header: LeaderboardHeader = serde_json::from_str(&contents)?;
+        if header.version != RECORDS_VERSION {
+            return Err(SaveError::UnsupportedVersion(header.version));
+        }
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn records(&self, difficulty: &Difficulty) -> &[Record] {
+        self.boards
+            .get(&difficulty.to_string())
+            .map_or(&[], |records| records.as_slice())
+    }
+
+    /// Adds `record` if it is among the best times. Returns its place, starting from 0.
+    pub fn add(&mut self, difficulty: &Difficulty, record: Record) -> Option<usize> {
+        let records = self.boards.entry(difficulty.to_string()).or_default();
+        let place = records.partition_point(|other| other.time <= record.time);
+        if place >= MAX_RECORDS {
+            return None;
+        }
+        records.insert(place, record);
+        records.truncate(MAX_RECORDS);
+        Some(place)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, CustomBoard, Settings};
+
+    fn record(time: f64) -> Record {
+        Record {
+            time,
+            date: 0,
+            bbbv_per_second: 1.0,
+            flags: false,
+        }
+    }
+
+    #[test]
+    fn keeps_best_times() {
+        let mut leaderboard = Leaderboard::default();
+        assert_eq!(leaderboard.add(&Difficulty::Easy, record(20.0)), Some(0));
+        assert_eq!(leaderboard.add(&Difficulty::Easy, record(10.0)), Some(0));
+        assert_eq!(leaderboard.add(&Difficulty::Easy, record(15.0)), Some(1));
+        let times: Vec<_> = leaderboard
+            .records(&Difficulty::Easy)
+            .iter()
+            .map(|record| record.time)
+            .collect();
+        assert_eq!(times, [10.0, 15.0, 20.0]);
+        assert!(leaderboard.records(&Difficulty::Hard).is_empty());
+
+        for i in 0..MAX_RECORDS {
+            leaderboard.add(&Difficulty::Easy, record(i as f64));
+        }
+        assert_eq!(leaderboard.records(&Difficulty::Easy).len(), MAX_RECORDS);
+        assert_eq!(leaderboard.add(&Difficulty::Easy, record(30.0)), None);
+    }
+
+    /// A 2x1 game, which the first click wins since it can't be a mine.
+    fn won_game(seed: Option<u64>) -> Game {
+        let mut game = Game::new(Settings {
+            difficulty: Difficulty::Custom(CustomBoard::new(2, 1, 1)),
+            seed,
+            ..Settings::default()
+        });
+        game.action(Action::Open(0, 0)).unwrap();
+        assert_eq!(game.state(), GameState::Won);
+        game
+    }
+
+    #[test]
+    fn only_clean_wins() {
+        let mut game = won_game(None);
+        assert!(Record::new(&game).is_some());
+
+        // Undoing the win and winning again doesn't count.
+        game.action(Action::Undo).unwrap();
+        assert_eq!(Record::new(&game), None);
+        game.action(Action::Redo).unwrap();
+        assert_eq!(game.state(), GameState::Won);
+        assert_eq!(Record::new(&game), None);
+    }
+
+    #[test]
+    fn no_chosen_boards() {
+        assert_eq!(Record::new(&won_game(Some(1))), None);
+
+        let settings = Settings {
+            difficulty: Difficulty::Custom(CustomBoard::new(3, 1, 1)),
+            ..Settings::default()
+        };
+        let mut game = Game::with_layout(settings, vec![vec![true, false, false]]).unwrap();
+        game.action(Action::Open(2, 0)).unwrap();
+        assert_eq!(game.state(), GameState::Won);
+        assert_eq!(Record::new(&game), None);
+    }
+
+    #[test]
+    fn custom_boards_are_separate() {
+        let mut leaderboard = Leaderboard::default();
+        let small = Difficulty::Custom(CustomBoard::new(5, 5, 5));
+        let dense = Difficulty::Custom(CustomBoard::new(5, 5, 10));
+        leaderboard.add(&small, record(3.0));
+        assert_eq!(leaderboard.records(&small).len(), 1);
+        assert!(leaderboard.records(&dense).is_empty());
+        // The same size and mines as Easy is a custom board of its own.
+        let easy_size = Difficulty::Custom(CustomBoard::new(
+            Difficulty::Easy.width(),
+            Difficulty::Easy.height(),
+            Difficulty::Easy.mines(),
+        ));
+        leaderboard.add(&easy_size, record(3.0));
+        assert!(leaderboard.records(&Difficulty::Easy).is_empty());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path =
+            std::env::temp_dir().join(format!("minesweeper-records-{}.json", std::process::id()));
+        assert_eq!(Leaderboard::load(&path).unwrap(), Leaderboard::default());
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.add(&Difficulty::Medium, record(42.0));
+        leaderboard.save(&path).unwrap();
+        let loaded = Leaderboard::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, leaderboard);
+    }
+
+    #[test]
+    fn dates() {
+        let date = |date| {
+            Record {
+                date,
+                ..record(0.0)
+            }
+            .date_string()
+        };
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(1_700_000_000), "2023-11-14");
+    }
+}
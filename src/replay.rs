@@ -0,0 +1,233 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Action, BoardError, Game, GameState, SaveError, Settings};
+
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayEvent {
+    /// Seconds on the game timer when the action was taken.
+    pub time: f64,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    version: u32,
+    pub settings: Settings,
+    pub mines: Vec<Vec<bool>>,
+    pub events: Vec<ReplayEvent>,
+    pub result: GameState,
+    pub time: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplayHeader {
+    version: u32,
+}
+
+impl Replay {
+    /// Builds a replay of `game` from the actions that were taken on it. Returns `None` if
+    /// no board has been generated yet.
+    pub fn new(game: &Game, settings: Settings, events: Vec<ReplayEvent>) -> Option<Self> {
+        Some(Replay {
+            version: REPLAY_VERSION,
+            settings,
+            mines: game.layout()?.clone(),
+            events,
+            result: game.state(),
+            time: game.time(),
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        let contents = fs::read_to_string(path)?;
+        let header: ReplayHeader = serde_json::from_str(&contents)?;
+        if header.version != REPLAY_VERSION {
+            return Err(SaveError::UnsupportedVersion(header.version));
+        }
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// A new game on the recorded board, before any action has been played.
+    pub fn game(&self) -> Result<Game, BoardError> {
+        Game::with_layout(self.settings.clone(), self.mines.clone())
+    }
+
+    pub fn player(&self) -> Result<ReplayPlayer<'_>, BoardError> {
+        Ok(ReplayPlayer {
+            replay: self,
+            game: self.game()?,
+            next: 0,
+        })
+    }
+}
+
+/// Feeds the actions of a [`Replay`] back through [`Game::action`] one at a time.
+pub struct ReplayPlayer<'a> {
+    replay: &'a Replay,
+    game: Game,
+    next: usize,
+}
+
+impl<'a> ReplayPlayer<'a> {
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// The event that the next call to `step` will play.
+    pub fn peek(&self) -> Option<&'a ReplayEvent> {
+        self.replay.events.get(self.next)
+    }
+
+    /// Plays the next event. Only actions the game accepted are recorded, so errors can only
+    /// come from a replay that doesn't match its board, and are ignored.
+    pub fn step(&mut self) -> Option<&'a ReplayEvent> {
+        let event = self.peek()?;
+        self.next += 1;
+        self.game.action(event.action.clone()).ok();
+        Some(event)
+    }
+
+    pub fn finish(mut self) -> Game {
+        while self.step().is_some() {}
+        self.game
+    }
+}
+
+/// Collects the actions of each game played by the [`Engine`](crate::Engine) and writes
+/// them to a replay file in `dir` when the game ends or is abandoned.
+pub(crate) struct Recorder {
+    dir: PathBuf,
+    settings: Settings,
+    events: Vec<ReplayEvent>,
+    started: SystemTime,
+}
+
+impl Recorder {
+    pub fn new(dir: PathBuf, game: &Game) -> Self {
+        Recorder {
+            dir,
+            settings: game.settings().clone(),
+            events: Vec::new(),
+            started: SystemTime::now(),
+        }
+    }
+
+    pub fn record(&mut self, game: &Game, action: &Action) {
+        if self.events.is_empty() {
+            self.settings = game.settings().clone();
+            self.started = SystemTime::now();
+        }
+        self.events.push(ReplayEvent {
+            time: game.time(),
+            action: action.clone(),
+        });
+    }
+
+    /// Writes the replay so far, overwriting any earlier write for the same game.
+    pub fn write(&self, game: &Game) -> Result<(), SaveError> {
+        match Replay::new(game, self.settings.clone(), self.events.clone()) {
+            Some(replay) => replay.save(self.path()),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts recording a new game.
+    pub fn restart(&mut self) {
+        self.events.clear();
+    }
+
+    fn path(&self) -> PathBuf {
+        let millis = self
+            .started
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_millis());
+        self.dir.join(format!("replay-{}.json", millis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomBoard, Difficulty};
+
+    fn settings() -> Settings {
+        Settings {
+            difficulty: Difficulty::Custom(CustomBoard::new(4, 3, 2)),
+            ..Settings::default()
+        }
+    }
+
+    fn layout() -> Vec<Vec<bool>> {
+        vec![
+            vec![true, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, true],
+        ]
+    }
+
+    #[test]
+    fn replay_reproduces_game() {
+        let mut game = Game::with_layout(settings(), layout()).unwrap();
+        let actions = [
+            Action::Open(3, 0),
+            Action::Flag(0, 0),
+            Action::Open(0, 0),
+            Action::Open(0, 2),
+            Action::Open(2, 2),
+        ];
+        let mut recorder = Recorder::new(std::env::temp_dir(), &game);
+        for action in actions {
+            recorder.record(&game, &action);
+            game.action(action).ok();
+        }
+        assert_eq!(game.state(), GameState::Won);
+
+        let replay = Replay::new(&game, settings(), recorder.events.clone()).unwrap();
+        assert_eq!(replay.result, GameState::Won);
+        let mut player = replay.player().unwrap();
+        assert_eq!(player.step().unwrap().action, Action::Open(3, 0));
+        assert_eq!(player.game().state(), GameState::Playing);
+        let replayed = player.finish();
+        assert_eq!(replayed.board(), game.board());
+        assert_eq!(replayed.state(), replay.result);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut game = Game::with_layout(settings(), layout()).unwrap();
+        game.action(Action::Open(0, 0)).unwrap();
+        let replay = Replay::new(
+            &game,
+            settings(),
+            vec![ReplayEvent {
+                time: 0.0,
+                action: Action::Open(0, 0),
+            }],
+        )
+        .unwrap();
+        let path =
+            std::env::temp_dir().join(format!("minesweeper-replay-{}.json", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.result, GameState::Lost);
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use crate::{
+    bot::{Bot, Strategy},
+    Game, GameState, Settings,
+};
+
+/// Plays a batch of games with the [`Bot`] on several threads, without any frontend.
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    settings: Settings,
+    games: usize,
+    threads: usize,
+    strategy: Strategy,
+}
+
+impl Simulation {
+    /// Plays `games` games with `settings`. If the settings have a seed, game `i` is
+    /// generated from that seed plus `i`, so runs can be repeated.
+    pub fn new(settings: Settings, games: usize) -> Self {
+        Simulation {
+            settings,
+            games,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            strategy: Strategy::default(),
+        }
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn run(&self) -> Report {
+        let next = AtomicUsize::new(0);
+        let reports: Vec<Report> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut report = Report::default();
+                        let mut bot = Bot::new(self.strategy).with_flags(false);
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            if i >= self.games {
+                                return report;
+                            }
+                            self.play(i, &mut bot, &mut report);
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().expect("simulation thread panicked"))
+                .collect()
+        });
+        let mut total = Report::default();
+        for report in reports {
+            total.merge(report);
+        }
+        total.errors.sort_by_key(|&(i, _)| i);
+        total
+    }
+
+    fn play(&self, i: usize, bot: &mut Bot, report: &mut Report) {
+        let mut settings = self.settings.clone();
+        settings.seed = settings.seed.map(|seed| seed.wrapping_add(i as u64));
+        let mut game = Game::new(settings);
+        // Each move opens or flags at least one cell, so a game that takes longer than this
+        // is stuck.
+        let max_moves = 2 * game.width() * game.height();
+        for _ in 0..max_moves {
+            if game.state().has_ended() {
+                break;
+            }
+            if let Err(err) = game.action(bot.next_action(&game)) {
+                report.errors.push((i, err.to_string()));
+                return;
+            }
+        }
+        if !game.state().has_ended() {
+            let err = format!("not finished after {} moves", max_moves);
+            report.errors.push((i, err));
+            return;
+        }
+        report.games += 1;
+        if game.state() == GameState::Won {
+            report.wins += 1;
+        }
+        report.total_time += game.time();
+        report.total_bbbv += game.stats().map_or(0, |stats| stats.board.bbbv);
+        report.total_guesses += bot.guesses();
+    }
+}
+
+/// Totals over the games of a [`Simulation`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    /// Games played to the end.
+    pub games: usize,
+    pub wins: usize,
+    pub total_time: f64,
+    pub total_bbbv: usize,
+    pub total_guesses: usize,
+    /// Games that were abandoned because an action the bot took failed or they never
+    /// ended, by game number.
+    pub errors: Vec<(usize, String)>,
+}
+
+impl Report {
+    pub fn win_rate(&self) -> f64 {
+        self.mean(self.wins as f64)
+    }
+
+    pub fn mean_time(&self) -> f64 {
+        self.mean(self.total_time)
+    }
+
+    pub fn mean_bbbv(&self) -> f64 {
+        self.mean(self.total_bbbv as f64)
+    }
+
+    pub fn mean_guesses(&self) -> f64 {
+        self.mean(self.total_guesses as f64)
+    }
+
+    fn mean(&self, total: f64) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            total / self.games as f64
+        }
+    }
+
+    fn merge(&mut self, other: Report) {
+        self.games += other.games;
+        self.wins += other.wins;
+        self.total_time += other.total_time;
+        self.total_bbbv += other.total_bbbv;
+        self.total_guesses += other.total_guesses;
+        self.errors.extend(other.errors);
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "Games:     {}", self.games)?;
+        writeln!(
+            f,
+            "Won:       {} ({:.1}%)",
+            self.wins,
+            100.0 * self.win_rate()
+        )?;
+        writeln!(f, "Mean time: {:.4}s", self.mean_time())?;
+        writeln!(f, "Mean 3BV:  {:.2}", self.mean_bbbv())?;
+        writeln!(f, "Guesses:   {:.2} per game", self.mean_guesses())?;
+        write!(f, "Errors:    {}", self.errors.len())?;
+        for (i, err) in &self.errors {
+            write!(f, "\n  game {}: {}", i, err)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Difficulty;
+
+    #[test]
+    fn plays_every_game() {
+        let settings = Settings {
+            difficulty: Difficulty::Easy,
+            seed: Some(100),
+            ..Settings::default()
+        };
+        let report = Simulation::new(settings.clone(), 40).with_threads(4).run();
+        assert_eq!(report.games, 40);
+        assert_eq!(report.errors, vec![]);
+        assert!(report.wins > 0);
+        assert!(report.mean_bbbv() > 0.0);
+
+        let again = Simulation::new(settings, 40).with_threads(1).run();
+        assert_eq!(again.wins, report.wins);
+        assert_eq!(again.total_bbbv, report.total_bbbv);
+    }
+}
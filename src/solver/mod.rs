@@ -0,0 +1,310 @@
+mod probability;
+#[cfg(test)]
+mod testing;
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::Cell;
+
+pub use probability::{probabilities, Probabilities};
+
+/// Cells whose contents follow from the numbers on the board.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Deductions {
+    pub safe: BTreeSet<(usize, usize)>,
+    pub mines: BTreeSet<(usize, usize)>,
+}
+
+impl Deductions {
+    pub fn is_empty(&self) -> bool {
+        self.safe.is_empty() && self.mines.is_empty()
+    }
+}
+
+/// The hidden cells around an open number and how many of them are mines. `cells` is
+/// sorted, so it can be searched.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Constraint {
+    cells: Vec<(usize, usize)>,
+    mines: usize,
+}
+
+/// Finds the hidden cells that are certainly safe or certainly mines, given a board as the
+/// player sees it and the number of mines not yet flagged, such as from
+/// [`Game::board`](crate::Game::board) and [`Game::mines_remaining`](crate::Game::mines_remaining).
+/// Flags are taken to be correct.
+///
+/// Each open number limits how many mines its hidden neighbours hold. A number whose limit
+/// is met or cannot be met otherwise decides all of its neighbours, and two overlapping
+/// numbers decide the cells only one of them touches when the difference in their counts
+/// leaves no choice. The mine count is treated as one more number covering every hidden
+/// cell. Deductions are fed back in until no more can be made.
+pub fn solve(board: &[Vec<Cell>], mines_remaining: usize) -> Deductions {
+    let mut deductions = Deductions::default();
+    loop {
+        let found = deduce(&constraints(board, mines_remaining, &deductions));
+        let before = deductions.safe.len() + deductions.mines.len();
+        deductions.safe.extend(found.safe);
+        deductions.mines.extend(found.mines);
+        if deductions.safe.len() + deductions.mines.len() == before {
+            return deductions;
+        }
+    }
+}
+
+fn deduce(constraints: &[Constraint]) -> Deductions {
+    let mut deductions = Deductions::default();
+    for constraint in constraints {
+        if constraint.mines == 0 {
+            deductions.safe.extend(&constraint.cells);
+        } else if constraint.mines == constraint.cells.len() {
+            deductions.mines.extend(&constraint.cells);
+        }
+    }
+
+    let mut touching: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (i, constraint) in constraints.iter().enumerate() {
+        for &cell in &constraint.cells {
+            touching.entry(cell).or_default().push(i);
+        }
+    }
+    for (i, a) in constraints.iter().enumerate() {
+        let others: BTreeSet<usize> = a
+            .cells
+            .iter()
+            .flat_map(|cell| touching[cell].iter().copied())
+            .filter(|&j| j != i)
+            .collect();
+        for j in others {
+            let b = &constraints[j];
+            // Cells only in `a` hold at least `a.mines - b.mines` mines, since `b` can't
+            // put more than `b.mines` mines into the shared cells. If `b` is a subset of
+            // `a`, they hold exactly that many.
+            let only_a: Vec<_> = a
+                .cells
+                .iter()
+                .filter(|cell| b.cells.binary_search(cell).is_err())
+                .copied()
+                .collect();
+            let only_b = b.cells.len() - (a.cells.len() - only_a.len());
+            if a.mines >= b.mines && a.mines - b.mines == only_a.len() {
+                deductions.mines.extend(only_a);
+                if only_b > 0 {
+                    let safe = b
+                        .cells
+                        .iter()
+                        .filter(|cell| a.cells.binary_search(cell).is_err());
+                    deductions.safe.extend(safe);
+                }
+            } else if only_b == 0 && a.mines == b.mines {
+                deductions.safe.extend(only_a);
+            }
+        }
+    }
+    deductions
+}
+
+/// The constraints from every open number and the mine count, leaving out cells that are
+/// already deduced.
+fn constraints(board: &[Vec<Cell>], mines_remaining: usize, known: &Deductions) -> Vec<Constraint> {
+    let mut constraints = number_constraints(board, known);
+    let unknown = unknown_cells(board, known);
+    if !unknown.is_empty() {
+        constraints.insert(Constraint {
+            cells: unknown,
+            mines: mines_remaining.saturating_sub(known.mines.len()),
+        });
+    }
+    constraints.into_iter().collect()
+}
+
+/// The hidden cells that are not already deduced, sorted like the cells of a [`Constraint`].
+fn unknown_cells(board: &[Vec<Cell>], known: &Deductions) -> Vec<(usize, usize)> {
+    let mut cells: Vec<_> = board
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, &cell)| (x, y, cell)))
+        .filter(|&(x, y, cell)| {
+            cell == Cell::Hidden && !known.safe.contains(&(x, y)) && !known.mines.contains(&(x, y))
+        })
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    cells.sort_unstable();
+    cells
+}
+
+fn number_constraints(board: &[Vec<Cell>], known: &Deductions) -> BTreeSet<Constraint> {
+    let mut constraints = BTreeSet::new();
+    for (y, row) in board.iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            if let Cell::Open(n) = cell {
+                let mut cells = Vec::new();
+                let mut mines = 0;
+                for (xn, yn) in neighbours(board, x, y) {
+                    match board[yn][xn] {
+                        Cell::Flag => mines += 1,
+                        Cell::Hidden if known.mines.contains(&(xn, yn)) => mines += 1,
+                        Cell::Hidden if !known.safe.contains(&(xn, yn)) => cells.push((xn, yn)),
+                        _ => (),
+                    }
+                }
+                if !cells.is_empty() {
+                    cells.sort_unstable();
+                    constraints.insert(Constraint {
+                        cells,
+                        mines: (n as usize).saturating_sub(mines),
+                    });
+                }
+            }
+        }
+    }
+    constraints
+}
+
+fn neighbours(board: &[Vec<Cell>], x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+    let (width, height) = (board[0].len(), board.len());
+    (y.saturating_sub(1)..(y + 2).min(height))
+        .flat_map(move |yn| (x.saturating_sub(1)..(x + 2).min(width)).map(move |xn| (xn, yn)))
+        .filter(move |&cell| cell != (x, y))
+}
+
+/// Whether `solve` alone can clear the board with these mines, starting from `first_click`.
+pub(crate) fn solvable(mines: &[Vec<bool>], first_click: (usize, usize)) -> bool {
+    let (width, height) = (mines[0].len(), mines.len());
+    let mut board = vec![vec![Cell::Hidden; width]; height];
+    let mut clear_remaining = mines.iter().flatten().filter(|&&mine| !mine).count();
+    let mut mines_remaining = width * height - clear_remaining;
+    let mut to_open = vec![first_click];
+    loop {
+        while let Some((x, y)) = to_open.pop() {
+            if board[y][x] != Cell::Hidden {
+                continue;
+            }
+            if mines[y][x] {
+                return false;
+            }
+            let count = neighbours(&board, x, y)
+                .filter(|&(xn, yn)| mines[yn][xn])
+                .count();
+            board[y][x] = Cell::Open(count as u8);
+            clear_remaining -= 1;
+            if count == 0 {
+                to_open.extend(neighbours(&board, x, y));
+            }
+        }
+        if clear_remaining == 0 {
+            return true;
+        }
+        let deductions = solve(&board, mines_remaining);
+        if deductions.is_empty() {
+            return false;
+        }
+        for &(x, y) in &deductions.mines {
+            board[y][x] = Cell::Flag;
+            mines_remaining -= 1;
+        }
+        to_open.extend(deductions.safe);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{testing::board, *};
+
+    fn cells(cells: &[(usize, usize)]) -> BTreeSet<(usize, usize)> {
+        cells.iter().copied().collect()
+    }
+
+    #[test]
+    fn single_cell_rules() {
+        let deductions = solve(&board("1.\n.."), 1);
+        assert!(deductions.is_empty());
+
+        let rows = "11.
+                    F1.
+                    11.";
+        let deductions = solve(&board(rows), 0);
+        assert_eq!(deductions.safe, cells(&[(2, 0), (2, 1), (2, 2)]));
+        assert!(deductions.mines.is_empty());
+
+        let deductions = solve(&board("2.\n.."), 2);
+        assert!(deductions.is_empty());
+        let deductions = solve(&board("3.\n.."), 3);
+        assert_eq!(deductions.mines, cells(&[(1, 0), (0, 1), (1, 1)]));
+    }
+
+    #[test]
+    fn constraint_cells_are_sorted() {
+        let board = board("1..\n...\n..1");
+        for constraint in constraints(&board, 2, &Deductions::default()) {
+            assert!(constraint.cells.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
+
+    #[test]
+    fn subset_rule() {
+        // The mine next to the left 1 is also the one next to the right 1, so its other
+        // cells are safe.
+        let deductions = solve(&board("11.\n..."), 1);
+        assert_eq!(deductions.safe, cells(&[(2, 0), (2, 1)]));
+        assert!(deductions.mines.is_empty());
+    }
+
+    #[test]
+    fn repeats_until_stuck() {
+        // The middle 1 shares a mine with each outer 1, which leaves one cell for it.
+        let rows = "1..
+                    1..
+                    1..";
+        let deductions = solve(&board(rows), 1);
+        assert_eq!(
+            deductions.safe,
+            cells(&[(1, 0), (1, 2), (2, 0), (2, 1), (2, 2)])
+        );
+        assert_eq!(deductions.mines, cells(&[(1, 1)]));
+    }
+
+    #[test]
+    fn one_two_pattern() {
+        // Each 1 can only cover one of the 2's mines, so the 2 has one on either side.
+        let deductions = solve(&board("121\n..."), 2);
+        assert_eq!(deductions.mines, cells(&[(0, 1), (2, 1)]));
+        assert_eq!(deductions.safe, cells(&[(1, 1)]));
+    }
+
+    #[test]
+    fn global_count() {
+        // The 1 holds one of the mines, and the cells out of its reach hold the rest.
+        let rows = "1..
+                    ...";
+        let deductions = solve(&board(rows), 1);
+        assert_eq!(deductions.safe, cells(&[(2, 0), (2, 1)]));
+        assert!(deductions.mines.is_empty());
+        let deductions = solve(&board(rows), 3);
+        assert_eq!(deductions.mines, cells(&[(2, 0), (2, 1)]));
+        assert!(deductions.safe.is_empty());
+
+        let deductions = solve(&board("F.."), 0);
+        assert_eq!(deductions.safe, cells(&[(1, 0), (2, 0)]));
+        let deductions = solve(&board("F.."), 2);
+        assert_eq!(deductions.mines, cells(&[(1, 0), (2, 0)]));
+    }
+
+    #[test]
+    fn solvable_boards() {
+        let mines = vec![
+            vec![false, false, false],
+            vec![false, false, false],
+            vec![false, false, true],
+        ];
+        assert!(solvable(&mines, (0, 0)));
+        assert!(!solvable(&mines, (2, 2)));
+
+        // A 50/50 in the top row.
+        let mines = vec![vec![true, false], vec![false, false], vec![false, false]];
+        let mut mirrored = mines.clone();
+        mirrored[0].swap(0, 1);
+        assert!(!solvable(&mines, (1, 2)));
+        assert!(!solvable(&mirrored, (1, 2)));
+    }
+}
@@ -0,0 +1,564 @@
+use std::collections::BTreeMap;
+
+use super::{neighbours, number_constraints, solve, unknown_cells, Constraint, Deductions};
+use crate::Cell;
+
+/// The chance that each hidden cell of a board is a mine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Probabilities {
+    cells: Vec<Vec<Option<f64>>>,
+}
+
+impl Probabilities {
+    /// The chance that the cell at (`x`, `y`) is a mine, or `None` if it isn't hidden.
+    pub fn get(&self, x: usize, y: usize) -> Option<f64> {
+        self.cells.get(y)?.get(x).copied().flatten()
+    }
+
+    pub fn cells(&self) -> &Vec<Vec<Option<f64>>> {
+        &self.cells
+    }
+
+    /// The hidden cell least likely to be a mine, preferring the first in reading order on
+    /// ties.
+    pub fn safest(&self) -> Option<(usize, usize)> {
+        let mut safest: Option<((usize, usize), f64)> = None;
+        for (y, row) in self.cells.iter().enumerate() {
+            for (x, &p) in row.iter().enumerate() {
+                if let Some(p) = p {
+                    if safest.is_none_or(|(_, best)| p < best) {
+                        safest = Some(((x, y), p));
+                    }
+                }
+            }
+        }
+        safest.map(|(cell, _)| cell)
+    }
+}
+
+/// Computes the chance that each hidden cell is a mine, given the same inputs as [`solve`].
+/// Every arrangement of the remaining mines that fits the numbers on the board is taken to
+/// be equally likely. Returns `None` if no arrangement fits.
+///
+/// Only the hidden cells next to a number (the frontier) have their arrangements
+/// enumerated, one group of cells linked by shared numbers at a time. The cells further
+/// in are weighted by the number of ways to place the rest of the mines among them.
+///
+/// The result is exact unless a group is too big to enumerate quickly. The numbers around
+/// such a group are ignored and its cells are treated like the ones further in.
+pub fn probabilities(board: &[Vec<Cell>], mines_remaining: usize) -> Option<Probabilities> {
+    let known = solve(board, mines_remaining);
+    if !fits(board, &known) {
+        return None;
+    }
+    let mines = mines_remaining.checked_sub(known.mines.len())?;
+    let unknown = unknown_cells(board, &known);
+    let (components, counts): (Vec<Component>, Vec<Counts>) =
+        components(number_constraints(board, &known))
+            .into_iter()
+            .filter_map(|component| {
+                let counts = component.enumerate()?;
+                Some((component, counts))
+            })
+            .unzip();
+    let frontier: usize = components.iter().map(|c| c.cells.len()).sum();
+    let interior = unknown.len() - frontier;
+
+    let ln_choose = LnChoose::new(unknown.len());
+    // Ways to place the mines left over by the frontier in the interior, scaled so that
+    // the largest is 1.
+    let interior_ways = {
+        let ln_ways: Vec<_> = (0..=mines)
+            .map(|frontier_mines| ln_choose.get(interior, mines - frontier_mines))
+            .collect();
+        let max = ln_ways.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        ln_ways
+            .into_iter()
+            .map(|ln| {
+                if ln.is_finite() {
+                    (ln - max).exp()
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let all = convolve_all(counts.iter().map(|c| c.ways.as_slice()));
+    let total: f64 = all
+        .iter()
+        .zip(&interior_ways)
+        .map(|(ways, interior)| ways * interior)
+        .sum();
+    if total == 0.0 || !total.is_finite() {
+        return None;
+    }
+
+    let mut cells: Vec<Vec<Option<f64>>> = board
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&cell| (cell == Cell::Hidden).then_some(0.0))
+                .collect()
+        })
+        .collect();
+    for &(x, y) in &known.mines {
+        cells[y][x] = Some(1.0);
+    }
+    if interior > 0 {
+        let expected: f64 = all
+            .iter()
+            .zip(&interior_ways)
+            .enumerate()
+            .map(|(frontier_mines, (ways, interior))| {
+                ways * interior * (mines - frontier_mines) as f64
+            })
+            .sum();
+        let p = expected / total / interior as f64;
+        for &(x, y) in &unknown {
+            cells[y][x] = Some(p);
+        }
+    }
+    for (i, component) in components.iter().enumerate() {
+        let others = convolve_all(
+            counts
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, c)| c.ways.as_slice()),
+        );
+        // Weight of the rest of the board for each number of mines in this component.
+        let weight: Vec<f64> = (0..counts[i].ways.len())
+            .map(|own| {
+                others
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(rest, ways)| Some(ways * interior_ways.get(own + rest)?))
+                    .sum()
+            })
+            .collect();
+        for (j, &(x, y)) in component.cells.iter().enumerate() {
+            let mine: f64 = counts[i]
+                .mines
+                .iter()
+                .zip(&weight)
+                .map(|(mines, weight)| mines[j] * weight)
+                .sum();
+            cells[y][x] = Some(mine / total);
+        }
+    }
+    Some(Probabilities { cells })
+}
+
+/// Whether every open number can still be met given the cells `known` decides. `solve`
+/// doesn't check this, so numbers that contradict each other can leave deductions that
+/// break them.
+fn fits(board: &[Vec<Cell>], known: &Deductions) -> bool {
+    if !known.safe.is_disjoint(&known.mines) {
+        return false;
+    }
+    board.iter().enumerate().all(|(y, row)| {
+        row.iter().enumerate().all(|(x, &cell)| match cell {
+            Cell::Open(n) => {
+                let (mut mines, mut undecided) = (0, 0);
+                for (xn, yn) in neighbours(board, x, y) {
+                    match board[yn][xn] {
+                        Cell::Flag => mines += 1,
+                        Cell::Hidden if known.mines.contains(&(xn, yn)) => mines += 1,
+                        Cell::Hidden if !known.safe.contains(&(xn, yn)) => undecided += 1,
+                        _ => (),
+                    }
+                }
+                mines <= n as usize && n as usize <= mines + undecided
+            }
+            _ => true,
+        })
+    })
+}
+
+/// Groups of frontier cells bigger than this are approximated instead of enumerated.
+const MAX_COMPONENT_CELLS: usize = 64;
+
+/// How many partial arrangements of one group are tried before giving up on enumerating it,
+/// so a frontier the numbers barely constrain can't stall a hint.
+const MAX_SEARCH_STEPS: usize = 1_000_000;
+
+/// Frontier cells linked by the numbers they share.
+#[derive(Debug, Clone, Default)]
+struct Component {
+    cells: Vec<(usize, usize)>,
+    /// Indices into `cells` and how many of them are mines.
+    constraints: Vec<(Vec<usize>, usize)>,
+}
+
+/// Arrangements of a component, by the number of mines in them. Scaled so that the largest
+/// count is 1, so that counts of big components don't overflow when multiplied.
+struct Counts {
+    /// The number of arrangements with that many mines.
+    ways: Vec<f64>,
+    /// How many of those arrangements have a mine in each cell.
+    mines: Vec<Vec<f64>>,
+}
+
+fn components(constraints: impl IntoIterator<Item = Constraint>) -> Vec<Component> {
+    let constraints: Vec<_> = constraints.into_iter().collect();
+    let mut parent: Vec<usize> = (0..constraints.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut first_seen = BTreeMap::new();
+    for (i, constraint) in constraints.iter().enumerate() {
+        for &cell in &constraint.cells {
+            let j = *first_seen.entry(cell).or_insert(i);
+            let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+            parent[a] = b;
+        }
+    }
+
+    let mut components: BTreeMap<usize, Component> = BTreeMap::new();
+    for (i, constraint) in constraints.into_iter().enumerate() {
+        let component = components.entry(root(&mut parent, i)).or_default();
+        let indices = constraint
+            .cells
+            .iter()
+            .map(
+                |cell| match component.cells.iter().position(|c| c == cell) {
+                    Some(index) => index,
+                    None => {
+                        component.cells.push(*cell);
+                        component.cells.len() - 1
+                    }
+                },
+            )
+            .collect();
+        component.constraints.push((indices, constraint.mines));
+    }
+    components.into_values().collect()
+}
+
+impl Component {
+    /// Counts the arrangements of the component, or `None` if it is too big to.
+    fn enumerate(&self) -> Option<Counts> {
+        let n = self.cells.len();
+        if n > MAX_COMPONENT_CELLS {
+            return None;
+        }
+        let mut touching = vec![Vec::new(); n];
+        for (c, (cells, _)) in self.constraints.iter().enumerate() {
+            for &cell in cells {
+                touching[cell].push(c);
+            }
+        }
+        let mut search = Search {
+            touching,
+            needed: self.constraints.iter().map(|&(_, mines)| mines).collect(),
+            unassigned: self
+                .constraints
+                .iter()
+                .map(|(cells, _)| cells.len())
+                .collect(),
+            assignment: vec![false; n],
+            ways: vec![0.0; n + 1],
+            mines: vec![vec![0.0; n]; n + 1],
+            steps: MAX_SEARCH_STEPS,
+        };
+        if !search.visit(0, 0) {
+            return None;
+        }
+
+        let max = search.ways.iter().copied().fold(0.0, f64::max);
+        if max > 0.0 {
+            for (ways, mines) in search.ways.iter_mut().zip(&mut search.mines) {
+                *ways /= max;
+                mines.iter_mut().for_each(|m| *m /= max);
+            }
+        }
+        Some(Counts {
+            ways: search.ways,
+            mines: search.mines,
+        })
+    }
+}
+
+/// Depth-first search over the mine/no-mine choices for each cell of a component, cutting
+/// off branches that break a constraint.
+struct Search {
+    touching: Vec<Vec<usize>>,
+    /// Mines still to be placed for each constraint.
+    needed: Vec<usize>,
+    /// Cells still to be decided for each constraint.
+    unassigned: Vec<usize>,
+    assignment: Vec<bool>,
+    ways: Vec<f64>,
+    mines: Vec<Vec<f64>>,
+    /// Calls to `visit` left before the search gives up.
+    steps: usize,
+}
+
+impl Search {
+    /// Returns false if the search ran out of steps.
+    fn visit(&mut self, cell: usize, placed: usize) -> bool {
+        if self.steps == 0 {
+            return false;
+        }
+        self.steps -= 1;
+        if cell == self.assignment.len() {
+            self.ways[placed] += 1.0;
+            for (count, &mine) in self.mines[placed].iter_mut().zip(&self.assignment) {
+                if mine {
+                    *count += 1.0;
+                }
+            }
+            return true;
+        }
+        for mine in [false, true] {
+            let fits = self.touching[cell].iter().all(|&c| {
+                let needed = self.needed[c];
+                if mine {
+                    needed > 0
+                } else {
+                    needed < self.unassigned[c]
+                }
+            });
+            if !fits {
+                continue;
+            }
+            for &c in &self.touching[cell] {
+                self.unassigned[c] -= 1;
+                if mine {
+                    self.needed[c] -= 1;
+                }
+            }
+            self.assignment[cell] = mine;
+            let finished = self.visit(cell + 1, placed + mine as usize);
+            for &c in &self.touching[cell] {
+                self.unassigned[c] += 1;
+                if mine {
+                    self.needed[c] += 1;
+                }
+            }
+            if !finished {
+                return false;
+            }
+        }
+        self.assignment[cell] = false;
+        true
+    }
+}
+
+/// The number of ways to combine independent groups, by total number of mines.
+fn convolve_all<'a>(groups: impl Iterator<Item = &'a [f64]>) -> Vec<f64> {
+    groups.fold(vec![1.0], |acc, ways| {
+        let mut result = vec![0.0; acc.len() + ways.len() - 1];
+        for (i, a) in acc.iter().enumerate() {
+            for (j, b) in ways.iter().enumerate() {
+                result[i + j] += a * b;
+            }
+        }
+        result
+    })
+}
+
+/// Natural logarithms of binomial coefficients, so that choosing mines among thousands of
+/// cells doesn't overflow.
+struct LnChoose {
+    ln_factorial: Vec<f64>,
+}
+
+impl LnChoose {
+    fn new(max: usize) -> Self {
+        let mut ln_factorial = vec![0.0; max + 1];
+        for n in 1..=max {
+            ln_factorial[n] = ln_factorial[n - 1] + (n as f64).ln();
+        }
+        LnChoose { ln_factorial }
+    }
+
+    /// ln(n choose k), or negative infinity if there are no ways.
+    fn get(&self, n: usize, k: usize) -> f64 {
+        if k > n {
+            f64::NEG_INFINITY
+        } else {
+            self.ln_factorial[n] - self.ln_factorial[k] - self.ln_factorial[n - k]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bot::Bot, generator::SeededRandom, solver::testing::board, Action, Difficulty, Game,
+        GameState, Settings,
+    };
+
+    /// Counts every arrangement of mines over the hidden cells that fits the board.
+    fn brute_force(board: &[Vec<Cell>], mines: usize) -> Vec<Vec<Option<f64>>> {
+        let (width, height) = (board[0].len(), board.len());
+        let hidden: Vec<_> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| board[y][x] == Cell::Hidden)
+            .collect();
+        let mut counts = vec![0usize; hidden.len()];
+        let mut total = 0;
+        for mask in 0u32..1 << hidden.len() {
+            if mask.count_ones() as usize != mines {
+                continue;
+            }
+            let is_mine = |x: usize, y: usize| match board[y][x] {
+                Cell::Flag => true,
+                Cell::Hidden => {
+                    let i = hidden.iter().position(|&cell| cell == (x, y)).unwrap();
+                    mask & 1 << i != 0
+                }
+                _ => false,
+            };
+            let fits = (0..height).all(|y| {
+                (0..width).all(|x| match board[y][x] {
+                    Cell::Open(n) => {
+                        neighbours(board, x, y)
+                            .filter(|&(xn, yn)| is_mine(xn, yn))
+                            .count()
+                            == n as usize
+                    }
+                    _ => true,
+                })
+            });
+            if fits {
+                total += 1;
+                for (i, count) in counts.iter_mut().enumerate() {
+                    if mask & 1 << i != 0 {
+                        *count += 1;
+                    }
+                }
+            }
+        }
+        let mut cells = vec![vec![None; width]; height];
+        for (&(x, y), count) in hidden.iter().zip(counts) {
+            cells[y][x] = Some(count as f64 / total as f64);
+        }
+        cells
+    }
+
+    fn assert_close(actual: &Probabilities, expected: &[Vec<Option<f64>>]) {
+        for (actual, expected) in actual
+            .cells()
+            .iter()
+            .flatten()
+            .zip(expected.iter().flatten())
+        {
+            match (actual, expected) {
+                (Some(a), Some(e)) => assert!((a - e).abs() < 1e-9, "{:?} != {:?}", a, e),
+                _ => assert_eq!(actual, expected),
+            }
+        }
+    }
+
+    #[test]
+    fn fifty_fifty() {
+        let board = board("..\n11\n00");
+        let probabilities = probabilities(&board, 1).unwrap();
+        assert_eq!(probabilities.get(0, 0), Some(0.5));
+        assert_eq!(probabilities.get(1, 0), Some(0.5));
+        assert_eq!(probabilities.get(0, 1), None);
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let boards = [
+            ("1..\n...", 1),
+            ("1..\n...", 2),
+            ("1..\n...", 3),
+            ("121\n...\n...", 3),
+            (".1..\n.2..\n.1..\n....", 4),
+            ("F2...\n12...\n.....\n..1..", 3),
+            ("....\n.21.\n.12.\n....", 4),
+        ];
+        for (rows, mines) in boards {
+            let board = board(rows);
+            assert_close(
+                &probabilities(&board, mines).expect(rows),
+                &brute_force(&board, mines),
+            );
+        }
+    }
+
+    #[test]
+    fn inconsistent_board() {
+        assert_eq!(probabilities(&board("3.\n.."), 1), None);
+        assert_eq!(probabilities(&board("1.\n.."), 0), None);
+    }
+
+    #[test]
+    fn safest_cell() {
+        let probabilities = probabilities(&board("1..\n..."), 3).unwrap();
+        assert_eq!(probabilities.safest(), Some((1, 0)));
+        assert_eq!(probabilities.get(2, 0), Some(1.0));
+    }
+
+    #[test]
+    fn hard_board() {
+        let mut game = Game::with_generator(
+            Settings {
+                difficulty: Difficulty::Hard,
+                ..Settings::default()
+            },
+            SeededRandom::new(7),
+        );
+        game.action(Action::Open(15, 8)).unwrap();
+        let probabilities = probabilities(game.board(), game.mines_remaining()).unwrap();
+        let expected: f64 = probabilities.cells().iter().flatten().flatten().sum();
+        assert!((expected - game.mines_remaining() as f64).abs() < 1e-6);
+    }
+
+    /// A Hard game that the bot has played until about half the safe cells are open, which
+    /// is when the frontier is longest. `None` if the bot loses before then.
+    fn hard_mid_game(seed: u64) -> Option<Game> {
+        let mut game = Game::new(Settings {
+            difficulty: Difficulty::Hard,
+            seed: Some(seed),
+            ..Settings::default()
+        });
+        let mut bot = Bot::new(Default::default());
+        while game.state() != GameState::Lost {
+            let cells = game.board().iter().flatten();
+            if cells.filter(|cell| matches!(cell, Cell::Open(_))).count() >= 190 {
+                return Some(game);
+            }
+            game.action(bot.next_action(&game)).unwrap();
+        }
+        None
+    }
+
+    #[test]
+    fn mid_game_hard_boards() {
+        let games: Vec<_> = (0..20).filter_map(hard_mid_game).take(5).collect();
+        assert!(!games.is_empty());
+        for game in games {
+            let probabilities = probabilities(game.board(), game.mines_remaining()).unwrap();
+            let expected: f64 = probabilities.cells().iter().flatten().flatten().sum();
+            assert!((expected - game.mines_remaining() as f64).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn large_frontiers_are_approximated() {
+        // Every third column is safe and the rest share the mines, so there are far too many
+        // arrangements to enumerate. The first board has too many cells to try, and the
+        // second runs out of search steps.
+        for (width, mines) in [(98, 66), (47, 32)] {
+            let rows = format!("{0}\n{1}\n{0}", ".".repeat(width), "2".repeat(width));
+            let probabilities = probabilities(&board(&rows), mines).unwrap();
+            for y in [0, 2] {
+                for x in 0..width {
+                    let p = if x % 3 == 2 { 0.0 } else { 0.5 };
+                    assert_eq!(probabilities.get(x, y), Some(p));
+                }
+            }
+        }
+    }
+}
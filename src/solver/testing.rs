@@ -0,0 +1,17 @@
+use crate::Cell;
+
+/// Parses a board with one row per line: `.` hidden, `F` flag, digits open.
+pub fn board(rows: &str) -> Vec<Vec<Cell>> {
+    rows.lines()
+        .map(|row| {
+            row.trim()
+                .chars()
+                .map(|c| match c {
+                    '.' => Cell::Hidden,
+                    'F' => Cell::Flag,
+                    _ => Cell::Open(c.to_digit(10).unwrap() as u8),
+                })
+                .collect()
+        })
+        .collect()
+}
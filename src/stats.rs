@@ -0,0 +1,308 @@
+use std::{collections::BTreeMap, fmt::Write, fs, io, path::Path, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Difficulty, Game, GameState, SaveError};
+
+pub const STATS_VERSION: u32 = 1;
+
+/// Totals over every game finished on one difficulty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Tally {
+    pub played: usize,
+    pub won: usize,
+    pub lost: usize,
+    /// Wins since the last loss.
+    pub current_streak: usize,
+    pub best_streak: usize,
+    pub total_win_time: f64,
+    /// Time spent on every finished game, won or lost.
+    pub total_time: f64,
+}
+
+impl Tally {
+    pub fn average_win_time(&self) -> Option<f64> {
+        if self.won == 0 {
+            None
+        } else {
+            Some(self.total_win_time / self.won as f64)
+        }
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.played == 0 {
+            0.0
+        } else {
+            self.won as f64 / self.played as f64
+        }
+    }
+
+    fn add(&mut self, won: bool, time: f64) {
+        self.played += 1;
+        self.total_time += time;
+        if won {
+            self.won += 1;
+            self.total_win_time += time;
+            self.current_streak += 1;
+            self.best_streak = self.best_streak.max(self.current_streak);
+        } else {
+            self.lost += 1;
+            self.current_streak = 0;
+        }
+    }
+}
+
+/// Lifetime statistics for each difficulty. Custom boards are kept apart by size and number
+/// of mines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    version: u32,
+    difficulties: BTreeMap<String, Tally>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsHeader {
+    version: u32,
+}
+
+/// A file format [`Stats`] can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            _ => Err(format!("unknown export format: {}", s)),
+        }
+    }
+}
+
+/// One difficulty in an export, with the averages worked out.
+#[derive(Debug, Serialize)]
+struct ExportRow<'a> {
+    difficulty: &'a str,
+    played: usize,
+    won: usize,
+    lost: usize,
+    win_rate: f64,
+    current_streak: usize,
+    best_streak: usize,
+    average_win_time: Option<f64>,
+    total_time: f64,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            version: STATS_VERSION,
+            difficulties: BTreeMap::new(),
+        }
+    }
+}
+
+impl Stats {
+    /// Reads the stats at `path`, or empty ones if there is no file yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SaveError> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+        let header: StatsHeader = serde_json::from_str(&contents)?;
+        if header.version != STATS_VERSION {
+            return Err(SaveError::UnsupportedVersion(header.version));
+        }
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, difficulty: &Difficulty) -> Tally {
+        self.difficulties
+            .get(&difficulty.to_string())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Every difficulty that has been played, by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Tally)> {
+        self.difficulties
+            .iter()
+            .map(|(name, tally)| (name.as_str(), tally))
+    }
+
+    /// Counts `game` if it has ended. Returns whether it was counted.
+    pub fn add(&mut self, game: &Game) -> bool {
+        let won = match game.state() {
+            GameState::Won => true,
+            GameState::Lost => false,
+            _ => return false,
+        };
+        self.difficulties
+            .entry(game.settings().difficulty.to_string())
+            .or_default()
+            .add(won, game.time());
+        true
+    }
+
+    pub fn export(&self, path: impl AsRef<Path>, format: ExportFormat) -> Result<(), SaveError> {
+        let contents = match format {
+            ExportFormat::Json => serde_json::to_string_pretty(&self.rows())?,
+            ExportFormat::Csv => self.to_csv(),
+        };
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    fn rows(&self) -> Vec<ExportRow<'_>> {
+        self.iter()
+            .map(|(difficulty, tally)| ExportRow {
+                difficulty,
+                played: tally.played,
+                won: tally.won,
+                lost: tally.lost,
+                win_rate: tally.win_rate(),
+                current_streak: tally.current_streak,
+                best_streak: tally.best_streak,
+                average_win_time: tally.average_win_time(),
+                total_time: tally.total_time,
+            })
+            .collect()
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "difficulty,played,won,lost,win_rate,current_streak,best_streak,\
+             average_win_time,total_time\n",
+        );
+        for row in self.rows() {
+            let average = row
+                .average_win_time
+                .map_or(String::new(), |time| format!("{:.3}", time));
+            writeln!(
+                csv,
+                "{},{},{},{},{:.4},{},{},{},{:.3}",
+                row.difficulty,
+                row.played,
+                row.won,
+                row.lost,
+                row.win_rate,
+                row.current_streak,
+                row.best_streak,
+                average,
+                row.total_time
+            )
+            .expect("writing to a String can't fail");
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{generator::FixedLayout, Action, CustomBoard, Settings};
+
+    /// A finished 3x3 game with a mine in the top left corner.
+    fn finished_game(won: bool) -> Game {
+        let settings = Settings {
+            difficulty: Difficulty::Custom(CustomBoard::new(3, 3, 1)),
+            ..Settings::default()
+        };
+        let mut mines = vec![vec![false; 3]; 3];
+        mines[0][0] = true;
+        let mut game = Game::with_generator(settings, FixedLayout::new(mines));
+        if won {
+            game.action(Action::Open(2, 2)).unwrap();
+        } else {
+            game.action(Action::Open(1, 1)).unwrap();
+            game.action(Action::Open(0, 0)).unwrap();
+        }
+        assert!(game.state().has_ended());
+        game
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("minesweeper-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn streaks() {
+        let mut stats = Stats::default();
+        for won in [true, true, false, true, true, true, false, true] {
+            assert!(stats.add(&finished_game(won)));
+        }
+        assert!(!stats.add(&Game::default()));
+
+        let tally = stats.get(&Difficulty::Custom(CustomBoard::new(3, 3, 1)));
+        assert_eq!(tally.played, 8);
+        assert_eq!(tally.won, 6);
+        assert_eq!(tally.lost, 2);
+        assert_eq!(tally.current_streak, 1);
+        assert_eq!(tally.best_streak, 3);
+        assert!(tally.total_time >= tally.total_win_time);
+        assert!(tally.average_win_time().is_some());
+        assert_eq!(stats.get(&Difficulty::Easy), Tally::default());
+        assert_eq!(Tally::default().average_win_time(), None);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = temp_path("stats.json");
+        assert_eq!(Stats::load(&path).unwrap(), Stats::default());
+        let mut stats = Stats::default();
+        stats.add(&finished_game(true));
+        stats.save(&path).unwrap();
+        let loaded = Stats::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, stats);
+    }
+
+    #[test]
+    fn export() {
+        let mut stats = Stats::default();
+        stats.add(&finished_game(false));
+        stats.add(&finished_game(true));
+
+        let path = temp_path("export.csv");
+        stats.export(&path, ExportFormat::Csv).unwrap();
+        let csv = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("difficulty,played,won,lost,"));
+        assert!(lines[1].starts_with("3x3:1,2,1,1,0.5000,1,1,"));
+
+        let path = temp_path("export.json");
+        stats.export(&path, ExportFormat::Json).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(json[0]["difficulty"], "3x3:1");
+        assert_eq!(json[0]["won"], 1);
+        assert!(json[0]["average_win_time"].is_number());
+    }
+}
@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+const APP_DIR: &str = "minesweeper";
+
+/// Directory for files the game writes on its own, such as autosaves.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR))
+}
+
+/// Directory for files the player may edit, such as the config.
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR))
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.toml"))
+}
+
+pub fn autosave_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("autosave.json"))
+}
+
+pub fn replay_dir() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("replays"))
+}
+
+pub fn stats_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("stats.json"))
+}
+
+pub fn records_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("records.json"))
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use crate::{
+    bot::{Bot, Strategy},
+    Action, Game, GameError, UI,
+};
+
+/// A frontend that plays by itself with a [`Bot`], optionally showing the games on another
+/// frontend.
+pub struct BotUI {
+    bot: Bot,
+    delay: Duration,
+    games: usize,
+    display: Option<Box<dyn UI>>,
+}
+
+impl BotUI {
+    /// A bot that plays one game as fast as it can without showing it.
+    pub fn new(strategy: Strategy) -> Self {
+        BotUI {
+            bot: Bot::new(strategy),
+            delay: Duration::ZERO,
+            games: 1,
+            display: None,
+        }
+    }
+
+    pub fn with_bot(mut self, bot: Bot) -> Self {
+        self.bot = bot;
+        self
+    }
+
+    /// Waits `delay` before each move.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Plays `games` games in a row before quitting.
+    pub fn with_games(mut self, games: usize) -> Self {
+        self.games = games;
+        self
+    }
+
+    /// Shows the games and messages on `display`, which can also interrupt the bot.
+    pub fn with_display(mut self, display: Box<dyn UI>) -> Self {
+        self.display = Some(display);
+        self
+    }
+}
+
+impl UI for BotUI {
+    fn get_action(&mut self, game: &Game) -> Action {
+        if let Some(display) = &mut self.display {
+            if let Some(action) = display.spectate(game, self.delay) {
+                return action;
+            }
+        } else if !self.delay.is_zero() {
+            std::thread::sleep(self.delay);
+        }
+        if game.state().has_ended() {
+            self.games = self.games.saturating_sub(1);
+            if self.games == 0 {
+                return Action::Quit;
+            }
+        }
+        self.bot.next_action(game)
+    }
+
+    fn show_msg(&mut self, msg: &str) {
+        if let Some(display) = &mut self.display {
+            display.show_msg(msg);
+        }
+    }
+
+    fn show_error(&mut self, err: &GameError) {
+        if let Some(display) = &mut self.display {
+            display.show_error(err);
+        }
+    }
+
+    fn show_busy(&mut self, game: &Game, msg: &str) {
+        if let Some(display) = &mut self.display {
+            display.show_busy(game, msg);
+        }
+    }
+}
@@ -1,8 +1,14 @@
 use crate::{
-    game::{Action, Cell, Game},
+    game::{Action, Cell, Difficulty, Game},
+    records::Record,
+    stats::{ExportFormat, Stats},
     UI,
 };
-use std::io::{self, BufRead};
+use std::{
+    io::{self, BufRead},
+    thread,
+    time::Duration,
+};
 
 pub struct CLUI {}
 
@@ -26,6 +32,13 @@ impl CLUI {
             println!();
         }
         println!("{}", game.time_as_secs());
+        if let Some(hint) = game.hint() {
+            let kind = if hint.safe { "safe" } else { "best guess" };
+            println!("Hint: {} {} ({})", hint.x, hint.y, kind);
+        }
+        if let (true, Some(seed)) = (game.state().has_ended(), game.seed()) {
+            println!("Seed: {}", seed);
+        }
     }
 
     fn parse_coordinates(&self, intext: String) -> (Option<usize>, Option<usize>) {
@@ -40,6 +53,12 @@ impl CLUI {
     }
 }
 
+impl Default for CLUI {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl UI for CLUI {
     fn get_action(&mut self, game: &Game) -> Action {
         self.print_board(game);
@@ -53,9 +72,14 @@ impl UI for CLUI {
             match c {
                 'q' => return Action::Quit,
                 'r' => return Action::Reset,
+                'u' => return Action::Undo,
+                'U' => return Action::Redo,
+                'h' => return Action::Hint,
+                'l' => return Action::ShowRecords,
+                'p' => return Action::ShowStats,
                 'f' | 'o' => {
                     let (x, y) = self.parse_coordinates(intext);
-                    if x == None || y == None {
+                    if x.is_none() || y.is_none() {
                         println!("Please enter a valid command.");
                         continue;
                     }
@@ -74,4 +98,70 @@ impl UI for CLUI {
     fn show_msg(&mut self, msg: &str) {
         println!("{}", msg);
     }
+
+    fn show_records(&mut self, difficulty: &Difficulty, records: &[Record]) {
+        println!("Best times on {}:", difficulty);
+        if records.is_empty() {
+            println!("No wins yet");
+        }
+        for (i, record) in records.iter().enumerate() {
+            println!(
+                "{:>2}. {:>7.2}s  {}  {:.2} 3BV/s{}",
+                i + 1,
+                record.time,
+                record.date_string(),
+                record.bbbv_per_second,
+                if record.flags { "" } else { "  no flags" }
+            );
+        }
+    }
+
+    fn show_stats(&mut self, stats: &Stats) -> Option<ExportFormat> {
+        println!("Difficulty   Played    Won   Lost  Streak  Best  Avg win");
+        for (difficulty, tally) in stats.iter() {
+            let average = tally
+                .average_win_time()
+                .map_or("-".to_string(), |time| format!("{:.2}s", time));
+            println!(
+                "{:<10} {:>8} {:>6} {:>6} {:>7} {:>5} {:>8}",
+                difficulty,
+                tally.played,
+                tally.won,
+                tally.lost,
+                tally.current_streak,
+                tally.best_streak,
+                average
+            );
+        }
+        println!("Export as json or csv? Leave empty to skip.");
+        let intext = io::stdin().lock().lines().next().unwrap().unwrap();
+        match intext.trim() {
+            "" => None,
+            format => match format.parse() {
+                Ok(format) => Some(format),
+                Err(err) => {
+                    println!("{}", err);
+                    None
+                }
+            },
+        }
+    }
+
+    fn spectate(&mut self, game: &Game, wait: Duration) -> Option<Action> {
+        self.print_board(game);
+        thread::sleep(wait);
+        None
+    }
+
+    fn confirm(&mut self, prompt: &str) -> bool {
+        loop {
+            println!("{} [y/n]", prompt);
+            let intext = io::stdin().lock().lines().next().unwrap().unwrap();
+            match intext.trim() {
+                "y" | "Y" => return true,
+                "n" | "N" => return false,
+                _ => continue,
+            }
+        }
+    }
 }
@@ -1,5 +1,7 @@
+mod bot;
 mod clui;
 mod tui;
 
+pub use bot::BotUI;
 pub use clui::CLUI;
-pub use self::tui::TUI;
+pub use self::tui::{Keymap, KeymapError, Theme, TUI};
@@ -0,0 +1,93 @@
+use tui::{
+    style::Style,
+    text::{Span, Spans},
+};
+
+/// One entry in the key bar, such as `f flag`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyHint {
+    pub keys: String,
+    pub label: &'static str,
+    /// What to show instead of `label` when space is short.
+    pub short: &'static str,
+}
+
+impl KeyHint {
+    pub fn new(keys: impl Into<String>, label: &'static str, short: &'static str) -> Self {
+        KeyHint {
+            keys: keys.into(),
+            label,
+            short,
+        }
+    }
+
+    fn text(&self, short: bool) -> (&str, &str) {
+        (&self.keys, if short { self.short } else { self.label })
+    }
+}
+
+const SEPARATOR: &str = "  ";
+
+/// Lays `hints` out in `width` columns. If they don't fit, the short labels are used, and
+/// if that isn't enough the hints at the end are left out.
+pub fn fit(hints: &[KeyHint], width: usize, key_style: Style) -> Spans<'static> {
+    let length = |hints: &[KeyHint], short: bool| -> usize {
+        let text: usize = hints
+            .iter()
+            .map(|hint| {
+                let (keys, label) = hint.text(short);
+                keys.chars().count() + 1 + label.chars().count()
+            })
+            .sum();
+        text + SEPARATOR.len() * hints.len().saturating_sub(1)
+    };
+    let mut shown = hints;
+    let mut short = false;
+    while length(shown, short) > width {
+        if !short {
+            short = true;
+        } else {
+            shown = &shown[..shown.len() - 1];
+        }
+    }
+    let mut spans = Vec::new();
+    for (i, hint) in shown.iter().enumerate() {
+        if i > 0 {
+            spans.push(Span::raw(SEPARATOR));
+        }
+        let (keys, label) = hint.text(short);
+        spans.push(Span::styled(keys.to_string(), key_style));
+        spans.push(Span::raw(format!(" {}", label)));
+    }
+    Spans(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(spans: Spans) -> String {
+        spans.0.into_iter().map(|span| span.content).collect()
+    }
+
+    #[test]
+    fn shortens_to_fit() {
+        let hints = [
+            KeyHint::new("↑↓←→", "move", "move"),
+            KeyHint::new("g", "open", "open"),
+            KeyHint::new("Space", "open/chord", "o/c"),
+            KeyHint::new("q", "quit", "quit"),
+        ];
+        let style = Style::default();
+        assert_eq!(
+            text(fit(&hints, 80, style)),
+            "↑↓←→ move  g open  Space open/chord  q quit"
+        );
+        assert_eq!(
+            text(fit(&hints, 40, style)),
+            "↑↓←→ move  g open  Space o/c  q quit"
+        );
+        assert_eq!(text(fit(&hints, 20, style)), "↑↓←→ move  g open");
+        assert_eq!(text(fit(&hints, 5, style)), "");
+    }
+}
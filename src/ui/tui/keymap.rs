@@ -0,0 +1,560 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// What a key does on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Command {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+    Open,
+    Flag,
+    Chord,
+    OpenOrChord,
+    Undo,
+    Redo,
+    Hint,
+    Reset,
+    Quit,
+    Settings,
+    Records,
+    Stats,
+    Pause,
+}
+
+impl Command {
+    pub const ALL: [Command; 21] = [
+        Command::Up,
+        Command::Down,
+        Command::Left,
+        Command::Right,
+        Command::UpLeft,
+        Command::UpRight,
+        Command::DownLeft,
+        Command::DownRight,
+        Command::Open,
+        Command::Flag,
+        Command::Chord,
+        Command::OpenOrChord,
+        Command::Undo,
+        Command::Redo,
+        Command::Hint,
+        Command::Reset,
+        Command::Quit,
+        Command::Settings,
+        Command::Records,
+        Command::Stats,
+        Command::Pause,
+    ];
+
+    /// The name used for the command in the config file.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Up => "up",
+            Command::Down => "down",
+            Command::Left => "left",
+            Command::Right => "right",
+            Command::UpLeft => "up-left",
+            Command::UpRight => "up-right",
+            Command::DownLeft => "down-left",
+            Command::DownRight => "down-right",
+            Command::Open => "open",
+            Command::Flag => "flag",
+            Command::Chord => "chord",
+            Command::OpenOrChord => "open-or-chord",
+            Command::Undo => "undo",
+            Command::Redo => "redo",
+            Command::Hint => "hint",
+            Command::Reset => "reset",
+            Command::Quit => "quit",
+            Command::Settings => "settings",
+            Command::Records => "records",
+            Command::Stats => "stats",
+            Command::Pause => "pause",
+        }
+    }
+
+    /// How far a movement command moves the cursor.
+    pub fn offset(&self) -> Option<(isize, isize)> {
+        match self {
+            Command::Up => Some((0, -1)),
+            Command::Down => Some((0, 1)),
+            Command::Left => Some((-1, 0)),
+            Command::Right => Some((1, 0)),
+            Command::UpLeft => Some((-1, -1)),
+            Command::UpRight => Some((1, -1)),
+            Command::DownLeft => Some((-1, 1)),
+            Command::DownRight => Some((1, 1)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Command {
+    type Err = KeymapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Command::ALL
+            .into_iter()
+            .find(|command| command.name() == s)
+            .ok_or_else(|| KeymapError::UnknownCommand(s.to_string()))
+    }
+}
+
+/// A key with the modifiers that matter for bindings. Shift is part of the character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    const fn plain(code: KeyCode) -> Self {
+        Key {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        let mut modifiers = event.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT);
+        if !matches!(event.code, KeyCode::Char(_)) {
+            modifiers |= event.modifiers & KeyModifiers::SHIFT;
+        }
+        Key {
+            code: event.code,
+            modifiers,
+        }
+    }
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = KeymapError;
+
+    /// Parses a character, a key name such as `space`, `up` or `f5`, optionally after
+    /// `ctrl-`, `alt-` or `shift-`. A shifted letter is the same as the capital.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || KeymapError::InvalidKey(s.to_string());
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            if lower.starts_with("ctrl-") && rest.len() > 5 {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = &rest[5..];
+            } else if lower.starts_with("alt-") && rest.len() > 4 {
+                modifiers |= KeyModifiers::ALT;
+                rest = &rest[4..];
+            } else if lower.starts_with("shift-") && rest.len() > 6 {
+                modifiers |= KeyModifiers::SHIFT;
+                rest = &rest[6..];
+            } else {
+                break;
+            }
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some('↑'), None) => KeyCode::Up,
+            (Some('↓'), None) => KeyCode::Down,
+            (Some('←'), None) => KeyCode::Left,
+            (Some('→'), None) => KeyCode::Right,
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" | "ins" => KeyCode::Insert,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" | "pgup" => KeyCode::PageUp,
+                "pagedown" | "pgdn" => KeyCode::PageDown,
+                name => match name.strip_prefix('f').map(str::parse) {
+                    Some(Ok(n)) if (1..=12).contains(&n) => KeyCode::F(n),
+                    _ => return Err(invalid()),
+                },
+            },
+        };
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                if !c.is_alphabetic() {
+                    return Err(invalid());
+                }
+                modifiers.remove(KeyModifiers::SHIFT);
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            code => code,
+        };
+        Ok(Key { code, modifiers })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeymapError {
+    UnknownPreset(String),
+    UnknownCommand(String),
+    InvalidKey(String),
+    Conflict {
+        key: String,
+        first: Command,
+        second: Command,
+    },
+}
+
+impl std::error::Error for KeymapError {}
+
+impl Display for KeymapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            KeymapError::UnknownPreset(name) => write!(
+                f,
+                "unknown key preset '{}', expected one of {}",
+                name,
+                PRESETS.join(", ")
+            ),
+            KeymapError::UnknownCommand(name) => write!(f, "unknown command '{}'", name),
+            KeymapError::InvalidKey(key) => write!(f, "invalid key '{}'", key),
+            KeymapError::Conflict { key, first, second } => {
+                write!(f, "{} is bound to both {} and {}", key, first, second)
+            }
+        }
+    }
+}
+
+/// Quits from anywhere, so it can't be bound to anything else.
+pub const QUIT_KEY: Key = Key {
+    code: KeyCode::Char('c'),
+    modifiers: KeyModifiers::CONTROL,
+};
+
+/// The names of the built-in sets of bindings.
+pub const PRESETS: [&str; 4] = ["default", "vi", "wasd", "numpad"];
+
+/// Bindings every preset has.
+const COMMON: [(Key, Command); 5] = [
+    (Key::plain(KeyCode::Up), Command::Up),
+    (Key::plain(KeyCode::Down), Command::Down),
+    (Key::plain(KeyCode::Left), Command::Left),
+    (Key::plain(KeyCode::Right), Command::Right),
+    (Key::plain(KeyCode::Esc), Command::Pause),
+];
+
+fn preset(name: &str) -> Option<Vec<(Key, Command)>> {
+    let chars: &[(char, Command)] = match name {
+        "default" => &[
+            ('g', Command::Open),
+            ('f', Command::Flag),
+            ('h', Command::Chord),
+            (' ', Command::OpenOrChord),
+            ('u', Command::Undo),
+            ('U', Command::Redo),
+            ('i', Command::Hint),
+            ('r', Command::Reset),
+            ('q', Command::Quit),
+            ('s', Command::Settings),
+            ('l', Command::Records),
+            ('p', Command::Stats),
+        ],
+        "vi" => &[
+            ('k', Command::Up),
+            ('j', Command::Down),
+            ('h', Command::Left),
+            ('l', Command::Right),
+            ('y', Command::UpLeft),
+            ('o', Command::UpRight),
+            ('b', Command::DownLeft),
+            ('n', Command::DownRight),
+            ('g', Command::Open),
+            ('f', Command::Flag),
+            ('d', Command::Chord),
+            (' ', Command::OpenOrChord),
+            ('u', Command::Undo),
+            ('U', Command::Redo),
+            ('i', Command::Hint),
+            ('r', Command::Reset),
+            ('q', Command::Quit),
+            ('s', Command::Settings),
+            ('L', Command::Records),
+            ('p', Command::Stats),
+        ],
+        "wasd" => &[
+            ('w', Command::Up),
+            ('s', Command::Down),
+            ('a', Command::Left),
+            ('d', Command::Right),
+            ('q', Command::UpLeft),
+            ('e', Command::UpRight),
+            ('z', Command::DownLeft),
+            ('c', Command::DownRight),
+            ('g', Command::Open),
+            ('f', Command::Flag),
+            ('x', Command::Chord),
+            (' ', Command::OpenOrChord),
+            ('u', Command::Undo),
+            ('U', Command::Redo),
+            ('i', Command::Hint),
+            ('R', Command::Reset),
+            ('Q', Command::Quit),
+            ('o', Command::Settings),
+            ('l', Command::Records),
+            ('p', Command::Stats),
+        ],
+        "numpad" => &[
+            ('8', Command::Up),
+            ('2', Command::Down),
+            ('4', Command::Left),
+            ('6', Command::Right),
+            ('7', Command::UpLeft),
+            ('9', Command::UpRight),
+            ('1', Command::DownLeft),
+            ('3', Command::DownRight),
+            ('5', Command::OpenOrChord),
+            ('0', Command::Flag),
+            ('.', Command::Chord),
+            ('+', Command::Open),
+            ('u', Command::Undo),
+            ('U', Command::Redo),
+            ('i', Command::Hint),
+            ('r', Command::Reset),
+            ('q', Command::Quit),
+            ('s', Command::Settings),
+            ('l', Command::Records),
+            ('p', Command::Stats),
+        ],
+        _ => return None,
+    };
+    let keys = chars
+        .iter()
+        .map(|&(c, command)| (Key::plain(KeyCode::Char(c)), command));
+    Some(keys.chain(COMMON).collect())
+}
+
+/// Maps the keys pressed on the board to commands.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Key, Command)>,
+    lookup: HashMap<Key, Command>,
+}
+
+impl Keymap {
+    /// The bindings of the preset called `name`, with each command in `overrides` bound to
+    /// its key instead.
+    pub fn new<'a>(
+        name: &str,
+        overrides: impl IntoIterator<Item = (&'a String, &'a String)>,
+    ) -> Result<Self, KeymapError> {
+        let mut bindings =
+            preset(name).ok_or_else(|| KeymapError::UnknownPreset(name.to_string()))?;
+        for (command, key) in overrides {
+            let command: Command = command.parse()?;
+            let key: Key = key.parse()?;
+            bindings.retain(|&(_, bound)| bound != command);
+            bindings.push((key, command));
+        }
+        Self::from_bindings(bindings)
+    }
+
+    fn from_bindings(bindings: Vec<(Key, Command)>) -> Result<Self, KeymapError> {
+        let mut lookup = HashMap::new();
+        for &(key, command) in &bindings {
+            if key == QUIT_KEY && command != Command::Quit {
+                return Err(KeymapError::Conflict {
+                    key: key.to_string(),
+                    first: Command::Quit,
+                    second: command,
+                });
+            }
+            if let Some(first) = lookup.insert(key, command) {
+                return Err(KeymapError::Conflict {
+                    key: key.to_string(),
+                    first,
+                    second: command,
+                });
+            }
+        }
+        Ok(Keymap { bindings, lookup })
+    }
+
+    pub fn get(&self, event: KeyEvent) -> Option<Command> {
+        self.lookup.get(&Key::from(event)).copied()
+    }
+
+    /// The keys bound to `command`, in the order they were bound.
+    pub fn keys(&self, command: Command) -> impl Iterator<Item = Key> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |&&(_, bound)| bound == command)
+            .map(|&(key, _)| key)
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::new("default", []).expect("the default preset has no conflicts")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn presets_bind_every_command() {
+        for name in PRESETS {
+            let keymap = Keymap::new(name, []).unwrap();
+            for command in Command::ALL {
+                let diagonal = matches!(command.offset(), Some((dx, dy)) if dx != 0 && dy != 0);
+                if name == "default" && diagonal {
+                    continue;
+                }
+                assert!(
+                    keymap.keys(command).next().is_some(),
+                    "{} {}",
+                    name,
+                    command
+                );
+            }
+        }
+        assert_eq!(
+            Keymap::new("emacs", []).unwrap_err(),
+            KeymapError::UnknownPreset("emacs".to_string())
+        );
+    }
+
+    #[test]
+    fn lookup() {
+        let keymap = Keymap::default();
+        let shift = KeyModifiers::SHIFT;
+        assert_eq!(
+            keymap.get(key(KeyCode::Char('U'), shift)),
+            Some(Command::Redo)
+        );
+        assert_eq!(
+            keymap.get(key(KeyCode::Up, KeyModifiers::NONE)),
+            Some(Command::Up)
+        );
+        assert_eq!(
+            keymap.get(key(KeyCode::Char('f'), KeyModifiers::CONTROL)),
+            None
+        );
+
+        let vi = Keymap::new("vi", []).unwrap();
+        assert_eq!(
+            vi.get(key(KeyCode::Char('h'), KeyModifiers::NONE)),
+            Some(Command::Left)
+        );
+        let numpad = Keymap::new("numpad", []).unwrap();
+        assert_eq!(
+            numpad.get(key(KeyCode::Char('9'), KeyModifiers::NONE)),
+            Some(Command::UpRight)
+        );
+    }
+
+    #[test]
+    fn overrides() {
+        let overrides = |pairs: &[(&str, &str)]| -> BTreeMap<String, String> {
+            pairs
+                .iter()
+                .map(|&(command, key)| (command.to_string(), key.to_string()))
+                .collect()
+        };
+        let keymap = Keymap::new("default", &overrides(&[("flag", "ctrl-x")])).unwrap();
+        let ctrl_x = key(KeyCode::Char('x'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.get(ctrl_x), Some(Command::Flag));
+        assert_eq!(
+            keymap.get(key(KeyCode::Char('f'), KeyModifiers::NONE)),
+            None
+        );
+
+        let swapped = overrides(&[("flag", "g"), ("open", "f")]);
+        let keymap = Keymap::new("default", &swapped).unwrap();
+        assert_eq!(
+            keymap.get(key(KeyCode::Char('g'), KeyModifiers::NONE)),
+            Some(Command::Flag)
+        );
+
+        let err = Keymap::new("default", &overrides(&[("open", "f")])).unwrap_err();
+        assert_eq!(err.to_string(), "f is bound to both flag and open");
+        let err = Keymap::new("default", &overrides(&[("dig", "x")])).unwrap_err();
+        assert_eq!(err, KeymapError::UnknownCommand("dig".to_string()));
+        let err = Keymap::new("default", &overrides(&[("open", "ctrl-")])).unwrap_err();
+        assert_eq!(err, KeymapError::InvalidKey("ctrl-".to_string()));
+        let err = Keymap::new("default", &overrides(&[("flag", "ctrl-c")])).unwrap_err();
+        assert_eq!(err.to_string(), "Ctrl-c is bound to both quit and flag");
+        assert!(Keymap::new("default", &overrides(&[("quit", "ctrl-c")])).is_ok());
+    }
+
+    #[test]
+    fn key_names() {
+        for name in ["x", "Space", "ctrl-r", "Alt-Up", "Shift-Up", "f5", "PgUp"] {
+            let key: Key = name.parse().unwrap();
+            assert_eq!(key.to_string().parse::<Key>(), Ok(key));
+        }
+        assert_eq!("space".parse::<Key>().unwrap().to_string(), "Space");
+        assert_eq!("shift-Up".parse::<Key>().unwrap().to_string(), "Shift-↑");
+        assert_eq!("shift-u".parse::<Key>().unwrap().to_string(), "U");
+        assert!("shift-1".parse::<Key>().is_err());
+        assert!("f13".parse::<Key>().is_err());
+        assert!("shift".parse::<Key>().is_err());
+    }
+}
@@ -1,50 +1,74 @@
+mod keybar;
+mod keymap;
+mod settingsform;
+mod theme;
+mod viewport;
+
 use std::{
     io::{self, Stdout},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    event::{
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use tui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::Style,
     text::{Span, Spans, Text},
     widgets::{Block, Borders, Paragraph},
-    Terminal,
+    Frame, Terminal,
 };
 
-use crate::{game::Difficulty, Action, Cell, Game, Settings, UI};
-
-const TICK: Duration = Duration::from_millis(100);
-const ACTIVE_BORDER: Style = Style {
-    fg: Some(Color::White),
-    bg: None,
-    add_modifier: Modifier::empty(),
-    sub_modifier: Modifier::empty(),
-};
+use keybar::KeyHint;
+use keymap::{Command, Key, QUIT_KEY};
+pub use keymap::{Keymap, KeymapError};
+use settingsform::{FieldStyle, SFAction, SettingsForm};
+pub use theme::Theme;
+use viewport::Viewport;
 
-const INACTIVE_BORDER: Style = Style {
-    fg: None,
-    bg: None,
-    add_modifier: Modifier::DIM,
-    sub_modifier: Modifier::empty(),
+use crate::{
+    game::Difficulty,
+    records::Record,
+    stats::{ExportFormat, Stats},
+    Action, Cell, Game, GameState, Settings, UI,
 };
 
+const TICK: Duration = Duration::from_millis(100);
 pub struct TUI {
     state: State,
     status: String,
     terminal: Terminal<CrosstermBackend<Stdout>>,
     cursor: GameCursor,
+    settings_form: SettingsForm<'static>,
+    mouse: MouseButtons,
+    // Where the panes were last drawn, to find what was clicked.
+    board_area: Rect,
+    settings_area: Rect,
+    viewport: Viewport,
+    keymap: Keymap,
+    theme: Theme,
 }
 
 #[derive(Debug)]
 enum State {
     Game,
-    Settings(SettingsEditor),
+    Settings,
+}
+
+/// The buttons held down, so that pressing left and right together chords instead of opening
+/// or flagging when they are released.
+#[derive(Debug, Default)]
+struct MouseButtons {
+    left: bool,
+    right: bool,
+    chorded: bool,
 }
 
 #[derive(Debug)]
@@ -71,50 +95,6 @@ impl GameCursor {
     }
 }
 
-#[derive(Debug)]
-struct SettingsEditor {
-    settings: Settings,
-    cursor: SettingsCursor,
-}
-
-impl SettingsEditor {
-    fn new(settings: &Settings) -> Self {
-        SettingsEditor {
-            settings: settings.clone(),
-            cursor: SettingsCursor::Normal(0),
-        }
-    }
-
-    fn cursor_index(&self) -> usize {
-        match self.cursor {
-            SettingsCursor::Normal(i) => i,
-            SettingsCursor::Difficulty(_) => 3,
-        }
-    }
-}
-
-#[derive(Debug)]
-enum SettingsCursor {
-    Normal(usize),
-    Difficulty(EditDifficulty),
-}
-
-#[derive(Debug)]
-struct EditDifficulty {
-    old_difficulty: Difficulty,
-    stage: EditDifficultyStage,
-    width: usize,
-    height: usize,
-    mines: usize,
-}
-
-#[derive(Debug)]
-enum EditDifficultyStage {
-    Width,
-    Height,
-    Mines,
-}
-
 impl TUI {
     pub fn new(settings: &Settings) -> Self {
         let terminal = initialize_terminal().expect("Terminal initialization failed");
@@ -123,80 +103,234 @@ impl TUI {
             status: String::default(),
             terminal,
             cursor: GameCursor::new(settings.difficulty.width(), settings.difficulty.height()),
+            settings_form: SettingsForm::new(settings.clone(), FieldStyle::default()),
+            mouse: MouseButtons::default(),
+            board_area: Rect::default(),
+            settings_area: Rect::default(),
+            viewport: Viewport::default(),
+            keymap: Keymap::default(),
+            theme: Theme::default(),
         }
     }
 
+    /// Uses `keymap` for the keys on the board instead of the default bindings.
+    pub fn with_keymap(mut self, keymap: Keymap) -> Self {
+        self.keymap = keymap;
+        self
+    }
+
+    /// Draws with `theme` instead of the classic colours.
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.settings_form.set_theme(theme.name);
+        self.set_theme(theme);
+        self
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.settings_form.set_style(theme.field);
+        self.theme = theme;
+    }
+
     fn draw(&mut self, game: &Game) {
-        let (mut board_border_style, mut settings_border_style) =
-            (INACTIVE_BORDER, INACTIVE_BORDER);
-        match self.state {
-            State::Game => {
-                board_border_style = ACTIVE_BORDER;
-            }
-            State::Settings(_) => {
-                settings_border_style = ACTIVE_BORDER;
-            }
+        let (active, inactive) = (self.theme.active_border, self.theme.inactive_border);
+        let (board_border_style, settings_border_style) = match self.state {
+            State::Game => (active, inactive),
+            State::Settings => (inactive, active),
         };
 
+        let areas = layout(self.terminal.size().unwrap());
+        if let Some([_, board_area, _, _]) = areas {
+            let inner = Block::default().borders(Borders::ALL).inner(board_area);
+            self.viewport = self.viewport.fit(
+                (game.width(), game.height()),
+                // The HUD takes the first line.
+                (
+                    inner.width as usize,
+                    inner.height.saturating_sub(1) as usize,
+                ),
+                (self.cursor.x, self.cursor.y),
+            );
+        }
+        let viewport = self.viewport;
+        let theme = self.theme.clone();
         let board = self.draw_board(game).block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_style(board_border_style),
         );
-        let settings = self.draw_settings(game).block(
+        let settings = self.settings_form.render().block(
             Block::default()
                 .title("Settings")
                 .borders(Borders::ALL)
                 .border_style(settings_border_style),
         );
 
+        let status = match (game.seed(), game.hint()) {
+            _ if !self.status.is_empty() => self.status.clone(),
+            (seed, _) if game.state().has_ended() => {
+                let stats = game.stats().map(|stats| stats.to_string());
+                let seed = seed.map(|seed| format!("Seed: {}", seed));
+                stats
+                    .into_iter()
+                    .chain(seed)
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            }
+            (_, Some(hint)) if hint.safe => "Hint: this cell is safe".to_string(),
+            (_, Some(_)) => "Hint: no safe cell, this is the best guess".to_string(),
+            _ => String::new(),
+        };
+
+        let hints = self.key_hints(game);
+
+        let (mut drawn_board_area, mut drawn_settings_area) = (Rect::default(), Rect::default());
         self.terminal
             .draw(|f| {
                 let area = f.size();
-                if area.width < 25 || area.height < 14 {
-                    f.render_widget(
-                        Paragraph::new(Text::raw("Window too small\n Please resize.")),
-                        area,
-                    );
-                    return;
-                }
+                let [settings_area, board_area, status_area, keybar_area] = match areas {
+                    Some(areas) => areas,
+                    None => {
+                        f.render_widget(
+                            Paragraph::new(Text::raw("Window too small\n Please resize.")),
+                            area,
+                        );
+                        return;
+                    }
+                };
                 let outline = Block::default()
                     .title("Minesweeper")
                     .title_alignment(Alignment::Center)
                     .borders(Borders::ALL);
-                let inner_area = outline.inner(area);
                 f.render_widget(outline, area);
-
-                let (settings_area, board_area, status_area, keybar_area) = {
-                    let bars = Layout::default()
-                        .direction(Direction::Vertical)
-                        .constraints([
-                            Constraint::Min(10),
-                            Constraint::Length(1),
-                            Constraint::Length(1),
-                        ])
-                        .split(inner_area);
-                    let panes = Layout::default()
-                        .direction(Direction::Horizontal)
-                        .constraints([Constraint::Length(18), Constraint::Min(5)])
-                        .split(bars[0]);
-                    (panes[0], panes[1], bars[1], bars[2])
-                };
                 f.render_widget(settings, settings_area);
                 f.render_widget(board, board_area);
-                f.render_widget(Paragraph::new(Text::raw(self.status.as_str())), status_area);
-                f.render_widget(Paragraph::new(Text::raw("KeyBar: TODO")), keybar_area);
+                draw_scroll_indicators(f, board_area, &viewport, &theme);
+                f.render_widget(Paragraph::new(Text::raw(status)), status_area);
+                let keybar = keybar::fit(&hints, keybar_area.width as usize, theme.key);
+                f.render_widget(Paragraph::new(keybar), keybar_area);
+                drawn_board_area = board_area;
+                drawn_settings_area = settings_area;
             })
             .unwrap();
+        self.board_area = drawn_board_area;
+        self.settings_area = drawn_settings_area;
+    }
+
+    /// The keys that do something in the current state, most useful first.
+    fn key_hints(&self, game: &Game) -> Vec<KeyHint> {
+        if let State::Settings = self.state {
+            return if self.settings_form.is_editing() {
+                vec![
+                    KeyHint::new("0-9", "type", "type"),
+                    KeyHint::new("↑↓", "change digit", "change"),
+                    KeyHint::new("←→", "select digit", "digit"),
+                    KeyHint::new("Enter", "set", "set"),
+                    KeyHint::new("Esc", "cancel", "cancel"),
+                ]
+            } else {
+                vec![
+                    KeyHint::new("↑↓", "move", "move"),
+                    KeyHint::new("Space", "choose", "choose"),
+                    KeyHint::new("w", "apply", "apply"),
+                    KeyHint::new("q", "cancel", "cancel"),
+                    KeyHint::new("Enter", "edit number", "edit"),
+                ]
+            };
+        }
+        let mut commands = Vec::new();
+        let allow_undo = game.settings().allow_undo;
+        if game.is_paused() {
+            commands.extend([
+                (Command::Pause, "resume", "resume"),
+                (Command::Quit, "quit", "quit"),
+                (Command::Reset, "restart", "new"),
+            ]);
+        } else if game.state().has_ended() {
+            commands.push((Command::Reset, "restart", "restart"));
+            commands.push((Command::Quit, "quit", "quit"));
+            if allow_undo {
+                commands.push((Command::Undo, "undo", "undo"));
+            }
+        } else {
+            commands.extend([
+                (Command::Open, "open", "open"),
+                (Command::Flag, "flag", "flag"),
+                (Command::Chord, "chord", "chord"),
+                (Command::OpenOrChord, "open/chord", "o/c"),
+                (Command::Quit, "quit", "quit"),
+                (Command::Reset, "restart", "new"),
+                (Command::Pause, "pause", "pause"),
+            ]);
+            if allow_undo {
+                commands.push((Command::Undo, "undo", "undo"));
+                commands.push((Command::Redo, "redo", "redo"));
+            }
+            commands.push((Command::Hint, "hint", "hint"));
+        }
+        commands.extend([
+            (Command::Settings, "settings", "set"),
+            (Command::Records, "records", "rec"),
+            (Command::Stats, "stats", "stats"),
+        ]);
+
+        let first_key = |command| self.keymap.keys(command).next().map(|key| key.to_string());
+        let mut hints = Vec::new();
+        if !game.state().has_ended() && !game.is_paused() {
+            let moves: Option<Vec<String>> =
+                [Command::Up, Command::Down, Command::Left, Command::Right]
+                    .into_iter()
+                    .map(first_key)
+                    .collect();
+            if let Some(moves) = moves {
+                let joiner = if moves.iter().all(|key| key.chars().count() == 1) {
+                    ""
+                } else {
+                    "/"
+                };
+                hints.push(KeyHint::new(moves.join(joiner), "move", "move"));
+            }
+        }
+        hints.extend(commands.into_iter().filter_map(|(command, label, short)| {
+            first_key(command).map(|key| KeyHint::new(key, label, short))
+        }));
+        hints
+    }
+
+    /// The cell drawn at a screen position, if any.
+    fn cell_at(&self, column: u16, row: u16) -> Option<(usize, usize)> {
+        // Inside the border, below the HUD line and past the coordinates.
+        let viewport = &self.viewport;
+        let left = self.board_area.x + 1 + viewport.margin() as u16;
+        let top = self.board_area.y + 2 + viewport.ruler() as u16;
+        let x = column.checked_sub(left)? as usize;
+        let y = row.checked_sub(top)? as usize;
+        (x < viewport.width && y < viewport.height).then_some((viewport.x + x, viewport.y + y))
+    }
+
+    /// The line of the settings form drawn at a screen position, if any.
+    fn settings_line_at(&self, column: u16, row: u16) -> Option<usize> {
+        let area = Block::default()
+            .borders(Borders::ALL)
+            .inner(self.settings_area);
+        let inside = column >= area.x
+            && column < area.x + area.width
+            && row >= area.y
+            && row < area.y + area.height;
+        inside.then(|| (row - area.y) as usize)
     }
 
     fn draw_board<'a>(&self, game: &Game) -> Paragraph<'a> {
+        const NUMBERS: [&str; 8] = ["1", "2", "3", "4", "5", "6", "7", "8"];
+        let theme = &self.theme;
+        let cells = &theme.cells;
         // Draw HUD
+        let viewport = &self.viewport;
         let mines_remaining = game.mines_remaining();
-        let timer_width = game.width() - 3;
+        // The HUD stays the width of the pane when the board scrolls under it.
+        let timer_width = (viewport.margin() + viewport.width).saturating_sub(3);
         let mut hudline = vec![Span::styled(
             format!("{:0>3}", mines_remaining),
-            Style::default().fg(Color::Blue),
+            theme.mine_counter,
         )];
         hudline.push(Span::styled(
             if game.state().has_ended() {
@@ -206,246 +340,269 @@ impl TUI {
                 let time = game.time_as_secs();
                 format!("{:>timer_width$}", time, timer_width = timer_width)
             },
-            Style::default().fg(Color::Red),
+            theme.timer,
         ));
         // Draw board
-        const CLOSED_BG: Color = Color::Gray;
-        const OPENED_BG: Color = Color::DarkGray;
-        const CLOSED_BG_NEIGHBOR: Color = Color::LightYellow;
-        const OPENED_BG_NEIGHBOR: Color = Color::Yellow;
-        const CLOSED_BG_CURSOR: Color = Color::White;
-        const OPENED_BG_CURSOR: Color = Color::White;
         let board = game.board();
         let mut lines = Vec::new();
         lines.push(Spans(hudline));
-        for (y, row) in board.iter().enumerate() {
-            let mut cells = Vec::new();
-            for (x, cell) in row.iter().enumerate() {
-                let (closed_color, opened_color) = if x == self.cursor.x && y == self.cursor.y {
-                    (CLOSED_BG_CURSOR, OPENED_BG_CURSOR)
+        if game.is_paused() {
+            // Hide the board, so pausing can't be used to think without the clock running.
+            let width = viewport.margin() + viewport.width;
+            let height = viewport.ruler() + viewport.height;
+            for i in 0..height {
+                let text = if i == height / 2 { "PAUSED" } else { "" };
+                let line = format!("{:^width$}", text, width = width);
+                lines.push(Spans::from(Span::styled(line, theme.paused)));
+            }
+            return Paragraph::new(Text { lines });
+        }
+        if viewport.scrolling {
+            // Number every fifth column, where there's room.
+            let mut ruler = " ".repeat(viewport.margin());
+            let mut skip = 0;
+            for x in viewport.columns() {
+                if skip > 0 {
+                    skip -= 1;
+                } else if x % 5 == 0 && x.to_string().len() <= viewport.x + viewport.width - x {
+                    ruler.push_str(&x.to_string());
+                    skip = x.to_string().len() - 1;
+                } else {
+                    ruler.push(' ');
+                }
+            }
+            lines.push(Spans::from(Span::styled(ruler, theme.coordinates)));
+        }
+        for y in viewport.rows() {
+            let row = &board[y];
+            let mut spans = Vec::new();
+            if viewport.scrolling {
+                let label = format!("{:>width$} ", y, width = viewport.margin() - 1);
+                spans.push(Span::styled(label, theme.coordinates));
+            }
+            for x in viewport.columns() {
+                let cell = &row[x];
+                let (closed, opened) = if x == self.cursor.x && y == self.cursor.y {
+                    (cells.cursor, cells.cursor)
                 } else if x >= self.cursor.x.saturating_sub(1)
                     && x <= self.cursor.x + 1
                     && y >= self.cursor.y.saturating_sub(1)
                     && y <= self.cursor.y + 1
                 {
-                    (CLOSED_BG_NEIGHBOR, OPENED_BG_NEIGHBOR)
+                    (cells.closed_near_cursor, cells.opened_near_cursor)
                 } else {
-                    (CLOSED_BG, OPENED_BG)
+                    (cells.closed, cells.opened)
                 };
-                let (text, mut style) = match cell {
-                    Cell::Hidden => (" ", Style::default()),
-                    Cell::Flag => ("►", Style::default().fg(Color::LightGreen)),
-                    Cell::Mine => ("*", Style::default().fg(Color::Green)),
-                    Cell::FalseFlag => ("►", Style::default().fg(Color::Red)),
-                    Cell::TrippedMine => ("*", Style::default().fg(Color::Red)),
+                let (text, content) = match cell {
+                    Cell::Hidden => (cells.hidden, Style::default()),
+                    Cell::Flag => cells.flag,
+                    Cell::Mine => cells.mine,
+                    Cell::FalseFlag => cells.false_flag,
+                    Cell::TrippedMine => cells.tripped_mine,
                     Cell::Open(0) => (" ", Style::default()),
-                    Cell::Open(1) => ("1", Style::default().fg(Color::Blue)),
-                    Cell::Open(2) => ("2", Style::default().fg(Color::LightGreen)),
-                    Cell::Open(3) => ("3", Style::default().fg(Color::LightRed)),
-                    Cell::Open(4) => ("4", Style::default().fg(Color::LightBlue)),
-                    Cell::Open(5) => ("5", Style::default().fg(Color::Cyan)),
-                    Cell::Open(6) => ("6", Style::default().fg(Color::LightMagenta)),
-                    Cell::Open(7) => ("7", Style::default().fg(Color::LightCyan)),
-                    Cell::Open(8) => ("8", Style::default().fg(Color::Magenta)),
+                    &Cell::Open(n @ 1..=8) => {
+                        (NUMBERS[n as usize - 1], cells.numbers[n as usize - 1])
+                    }
                     _ => unreachable!(),
                 };
-                style = match cell {
-                    Cell::Hidden | Cell::Flag => style.bg(closed_color),
-                    _ => style.bg(opened_color),
-                };
+                let mut style = match cell {
+                    Cell::Hidden | Cell::Flag => closed,
+                    _ => opened,
+                }
+                .patch(content);
+                if let Some(hint) = game.hint().filter(|hint| (hint.x, hint.y) == (x, y)) {
+                    style = style.patch(if hint.safe {
+                        cells.hint_safe
+                    } else {
+                        cells.hint_guess
+                    });
+                }
                 if *cell == Cell::TrippedMine || *cell == Cell::FalseFlag {
-                    style = style.add_modifier(Modifier::RAPID_BLINK);
+                    style = style.patch(cells.mistake);
                 }
-                cells.push(Span::styled(text, style));
-            }
-            lines.push(Spans(cells));
-        }
-        Paragraph::new(Text { lines })
-    }
-
-    fn draw_settings<'a>(&self, game: &Game) -> Paragraph<'a> {
-        let settings = match &self.state {
-            State::Game => game.settings(),
-            State::Settings(editor) => &editor.settings,
-        };
-        let mut lines = Vec::new();
-        lines.push(Spans(vec![Span::styled(
-            "Difficulty:",
-            Style::default().add_modifier(Modifier::UNDERLINED),
-        )]));
-        lines.push(Spans(vec![]));
-        lines.push(Spans(vec![
-            Span::styled(" Easy       [", Style::default()),
-            Span::styled(
-                if settings.difficulty == Difficulty::Easy {
-                    "*"
-                } else {
-                    " "
-                },
-                Style::default().fg(Color::Red),
-            ),
-            Span::styled("]", Style::default()),
-        ]));
-        lines.push(Spans(vec![
-            Span::styled(" Medium     [", Style::default()),
-            Span::styled(
-                if settings.difficulty == Difficulty::Medium {
-                    "*"
-                } else {
-                    " "
-                },
-                Style::default().fg(Color::Red),
-            ),
-            Span::styled("]", Style::default()),
-        ]));
-        lines.push(Spans(vec![
-            Span::styled(" Hard       [", Style::default()),
-            Span::styled(
-                if settings.difficulty == Difficulty::Hard {
-                    "*"
-                } else {
-                    " "
-                },
-                Style::default().fg(Color::Red),
-            ),
-            Span::styled("]", Style::default()),
-        ]));
-        lines.push(Spans(vec![
-            Span::styled(" Custom     [", Style::default()),
-            Span::styled(
-                if let Difficulty::Custom(_) = settings.difficulty {
-                    "*"
-                } else {
-                    " "
-                },
-                Style::default().fg(Color::Red),
-            ),
-            Span::styled("]", Style::default()),
-        ]));
-        lines.push(Spans(vec![]));
-        lines.push(Spans(vec![
-            Span::styled(" Width      ", Style::default()),
-            Span::styled(
-                format!("{:0>3}", settings.difficulty.width()),
-                Style::default().fg(Color::LightGreen),
-            ),
-        ]));
-        lines.push(Spans(vec![
-            Span::styled(" Height     ", Style::default()),
-            Span::styled(
-                format!("{:0>3}", settings.difficulty.height()),
-                Style::default().fg(Color::LightGreen),
-            ),
-        ]));
-        lines.push(Spans(vec![
-            Span::styled(" Mines      ", Style::default()),
-            Span::styled(
-                format!("{:0>3}", settings.difficulty.mines()),
-                Style::default().fg(Color::LightGreen),
-            ),
-        ]));
-
-        if let State::Settings(editor) = &self.state {
-            let i = editor.cursor_index() + 2;
-            for i in lines[i].0.iter_mut() {
-                (*i).style = (*i)
-                    .clone()
-                    .style
-                    .patch(Style::default().bg(Color::White).fg(Color::Black));
+                spans.push(Span::styled(text, style));
             }
+            lines.push(Spans(spans));
         }
         Paragraph::new(Text { lines })
     }
 
     fn handle_event(&mut self, event: Event, game: &Game) -> Option<Action> {
-        use KeyCode::*;
         // Check global hotkeys
-        if event
-            == Event::Key(KeyEvent {
-                code: Char('c'),
-                modifiers: KeyModifiers::CONTROL,
-            })
-        {
-            return Some(Action::Quit);
+        if let Event::Key(key) = event {
+            if Key::from(key) == QUIT_KEY {
+                return Some(Action::Quit);
+            }
+        }
+        if let Event::Mouse(mouse) = event {
+            return self.handle_mouse(mouse, game);
+        }
+        if event == Event::FocusLost && game.state() == GameState::Playing && !game.is_paused() {
+            return Some(Action::Pause);
         }
         match &mut self.state {
             State::Game => match event {
-                Event::Key(KeyEvent { code, .. }) => match code {
-                    Char('q') => Some(Action::Quit),
-                    Char('r') => Some(Action::Reset),
-                    Up => {
-                        self.cursor.move_(0, -1);
-                        None
-                    }
-                    Down => {
-                        self.cursor.move_(0, 1);
-                        None
-                    }
-                    Left => {
-                        self.cursor.move_(-1, 0);
-                        None
-                    }
-                    Right => {
-                        self.cursor.move_(1, 0);
-                        None
-                    }
-                    // TODO: Handle Numpad
-                    Char('f') => Some(Action::Flag(self.cursor.x, self.cursor.y)),
-                    Char('g') => Some(Action::Open(self.cursor.x, self.cursor.y)),
-                    Char('h') => Some(Action::Chord(self.cursor.x, self.cursor.y)),
-                    Char(' ') => Some(Action::OpenOrChord(self.cursor.x, self.cursor.y)),
-                    Char('s') => {
-                        self.state = State::Settings(SettingsEditor::new(game.settings()));
-                        None
-                    }
-                    _ => {
-                        self.status = "Invalid key".to_string();
-                        None
-                    }
-                },
-                _ => None,
-            },
-            State::Settings(ref mut editor) => match editor.cursor {
-                SettingsCursor::Normal(i) => match event {
-                    Event::Key(KeyEvent { code, .. }) => match code {
-                        Up => {
-                            editor.cursor = SettingsCursor::Normal(i.saturating_sub(1));
-                            None
-                        }
-                        Down => {
-                            editor.cursor = SettingsCursor::Normal(std::cmp::min(3, i + 1));
-                            None
-                        }
-                        Char('q') => {
-                            self.state = State::Game;
-                            None
+                Event::Key(key) => {
+                    let (x, y) = (self.cursor.x, self.cursor.y);
+                    let command = match self.keymap.get(key) {
+                        Some(command) => command,
+                        None => {
+                            self.status = "Invalid key".to_string();
+                            return None;
                         }
-                        Char('w') => {
-                            let settings = editor.settings.clone();
-                            self.state = State::Game;
-                            if settings.difficulty != game.settings().difficulty {
-                                self.cursor = GameCursor::new(
-                                    settings.difficulty.width(),
-                                    settings.difficulty.height(),
-                                );
+                    };
+                    if game.is_paused() {
+                        match command {
+                            Command::Pause => return Some(Action::Resume),
+                            Command::Quit
+                            | Command::Reset
+                            | Command::Settings
+                            | Command::Records
+                            | Command::Stats => (),
+                            _ => {
+                                self.status = self.resume_message();
+                                return None;
                             }
-                            Some(Action::ChangeSettings(settings))
                         }
-                        Char(' ') | Enter => {
-                            editor.settings.difficulty = match editor.cursor_index() {
-                                0 => Difficulty::Easy,
-                                1 => Difficulty::Medium,
-                                2 => Difficulty::Hard,
-                                _ => todo!(),
-                            };
+                    }
+                    if let Some((dx, dy)) = command.offset() {
+                        self.cursor.move_(dx, dy);
+                        return None;
+                    }
+                    match command {
+                        Command::Open => Some(Action::Open(x, y)),
+                        Command::Flag => Some(Action::Flag(x, y)),
+                        Command::Chord => Some(Action::Chord(x, y)),
+                        Command::OpenOrChord => Some(Action::OpenOrChord(x, y)),
+                        Command::Undo => Some(Action::Undo),
+                        Command::Redo => Some(Action::Redo),
+                        Command::Hint => Some(Action::Hint),
+                        Command::Reset => Some(Action::Reset),
+                        Command::Quit => Some(Action::Quit),
+                        Command::Records => Some(Action::ShowRecords),
+                        Command::Stats => Some(Action::ShowStats),
+                        Command::Pause => Some(Action::Pause),
+                        Command::Settings => {
+                            self.state = State::Settings;
+                            self.settings_form.set(game.settings().clone());
+                            self.settings_form.focus();
                             None
                         }
                         _ => None,
-                    },
-                    _ => None,
-                },
-                SettingsCursor::Difficulty(_) => todo!(),
+                    }
+                }
+                _ => None,
             },
+            State::Settings => {
+                let action = self.settings_form.handle_input(event);
+                self.handle_form_action(action, game)
+            }
+        }
+    }
+
+    fn resume_message(&self) -> String {
+        match self.keymap.keys(Command::Pause).next() {
+            Some(key) => format!("Paused. Press {} to resume", key),
+            None => "Paused".to_string(),
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, game: &Game) -> Option<Action> {
+        let MouseEvent {
+            kind, column, row, ..
+        } = mouse;
+        if let (MouseEventKind::Down(MouseButton::Left), Some(line)) =
+            (kind, self.settings_line_at(column, row))
+        {
+            if let State::Game = self.state {
+                self.state = State::Settings;
+                self.settings_form.set(game.settings().clone());
+                self.settings_form.focus();
+            }
+            let action = self.settings_form.click(line);
+            return self.handle_form_action(action, game);
+        }
+        let cell = self.cell_at(column, row);
+        if game.is_paused() {
+            if cell.is_some() && matches!(kind, MouseEventKind::Down(_)) {
+                self.status = self.resume_message();
+            }
+            return None;
+        }
+        if let Some((x, y)) = cell {
+            self.cursor.x = x;
+            self.cursor.y = y;
+            if let (State::Settings, MouseEventKind::Down(_)) = (&self.state, kind) {
+                self.settings_form.unfocus();
+                self.handle_form_action(SFAction::Reset(game.settings().clone()), game);
+            }
+        }
+        let buttons = &mut self.mouse;
+        let chord = match kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                buttons.left = true;
+                buttons.chorded |= buttons.right;
+                buttons.right
+            }
+            MouseEventKind::Down(MouseButton::Right) => {
+                buttons.right = true;
+                buttons.chorded |= buttons.left;
+                buttons.left
+            }
+            MouseEventKind::Down(MouseButton::Middle) => true,
+            MouseEventKind::Up(button) => {
+                let released = match button {
+                    MouseButton::Left => std::mem::replace(&mut buttons.left, false),
+                    MouseButton::Right => std::mem::replace(&mut buttons.right, false),
+                    MouseButton::Middle => false,
+                };
+                let chorded = buttons.chorded;
+                if !buttons.left && !buttons.right {
+                    buttons.chorded = false;
+                }
+                if !released || chorded {
+                    return None;
+                }
+                let (x, y) = cell?;
+                return match button {
+                    MouseButton::Left => Some(Action::Open(x, y)),
+                    _ => Some(Action::Flag(x, y)),
+                };
+            }
+            _ => false,
+        };
+        let (x, y) = cell?;
+        chord.then_some(Action::Chord(x, y))
+    }
+
+    fn handle_form_action(&mut self, action: SFAction, game: &Game) -> Option<Action> {
+        // Show the theme picked in the form straight away. Cancelling picks the old one again.
+        if self.settings_form.theme() != self.theme.name {
+            let theme = Theme::named(self.settings_form.theme()).unwrap_or_default();
+            self.set_theme(theme);
+        }
+        match action {
+            SFAction::None => None,
+            SFAction::Change(_) => {
+                self.status = "Press w to apply or q to cancel".to_string();
+                None
+            }
+            SFAction::Invalid(err) => {
+                self.status = err.to_string();
+                None
+            }
+            SFAction::Set(settings) => {
+                self.state = State::Game;
+                if settings.difficulty != game.settings().difficulty {
+                    self.cursor =
+                        GameCursor::new(settings.difficulty.width(), settings.difficulty.height());
+                }
+                Some(Action::ChangeSettings(settings))
+            }
+            SFAction::Reset(_) | SFAction::Inactive => {
+                self.state = State::Game;
+                None
+            }
         }
     }
 }
@@ -458,11 +615,22 @@ impl Drop for TUI {
 
 impl UI for TUI {
     fn get_action(&mut self, game: &Game) -> Action {
+        if self.cursor.width != game.width() || self.cursor.height != game.height() {
+            self.cursor = GameCursor::new(game.width(), game.height());
+        }
+        if let State::Game = self.state {
+            self.settings_form.set(game.settings().clone());
+        }
         loop {
             self.draw(game);
             if event::poll(TICK).unwrap() {
-                self.status = String::default();
                 let event = event::read().unwrap();
+                if is_release_or_repeat(&event) {
+                    continue;
+                }
+                if !is_passive(&event) {
+                    self.status = String::default();
+                }
                 match self.handle_event(event, game) {
                     Some(action) => return action,
                     None => continue,
@@ -474,12 +642,248 @@ impl UI for TUI {
     fn show_msg(&mut self, msg: &str) {
         self.status = msg.to_string();
     }
+
+    fn show_busy(&mut self, game: &Game, msg: &str) {
+        let status = std::mem::replace(&mut self.status, msg.to_string());
+        self.draw(game);
+        self.status = status;
+    }
+
+    fn theme(&self) -> Option<&str> {
+        Some(self.theme.name)
+    }
+
+    fn spectate(&mut self, game: &Game, wait: Duration) -> Option<Action> {
+        if self.cursor.width != game.width() || self.cursor.height != game.height() {
+            self.cursor = GameCursor::new(game.width(), game.height());
+        }
+        let deadline = Instant::now() + wait;
+        loop {
+            self.draw(game);
+            let timeout = deadline.saturating_duration_since(Instant::now()).min(TICK);
+            if event::poll(timeout).unwrap() {
+                if let Event::Key(key) = event::read().unwrap() {
+                    // The same keys quit as when playing.
+                    let quit =
+                        Key::from(key) == QUIT_KEY || self.keymap.get(key) == Some(Command::Quit);
+                    if key.kind == KeyEventKind::Press && quit {
+                        return Some(Action::Quit);
+                    }
+                }
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+        }
+    }
+
+    fn show_records(&mut self, difficulty: &Difficulty, records: &[Record]) {
+        let mut text = format!("Best times on {}\n\n", difficulty);
+        if records.is_empty() {
+            text.push_str("No wins yet\n");
+        } else {
+            text.push_str("     Time        Date   3BV/s  Flags\n");
+        }
+        for (i, record) in records.iter().enumerate() {
+            text.push_str(&format!(
+                "{:>2}. {:>7.2}s  {}  {:>6.2}  {:>5}\n",
+                i + 1,
+                record.time,
+                record.date_string(),
+                record.bbbv_per_second,
+                if record.flags { "yes" } else { "no" }
+            ));
+        }
+        text.push_str("\nPress any key to go back");
+        let border_style = self.theme.active_border;
+        self.terminal
+            .draw(|f| {
+                let block = Block::default()
+                    .title("Records")
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_style(border_style);
+                f.render_widget(
+                    Paragraph::new(Text::raw(text.as_str()))
+                        .alignment(Alignment::Center)
+                        .block(block),
+                    f.size(),
+                );
+            })
+            .unwrap();
+        loop {
+            if let Event::Key(KeyEvent {
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read().unwrap()
+            {
+                return;
+            }
+        }
+    }
+
+    fn show_stats(&mut self, stats: &Stats) -> Option<ExportFormat> {
+        let mut text =
+            String::from("Difficulty   Played    Won   Lost  Streak  Best  Avg win    Total\n");
+        for (difficulty, tally) in stats.iter() {
+            let average = tally
+                .average_win_time()
+                .map_or("-".to_string(), |time| format!("{:.2}s", time));
+            text.push_str(&format!(
+                "{:<10} {:>8} {:>6} {:>6} {:>7} {:>5} {:>8} {:>7.0}s\n",
+                difficulty,
+                tally.played,
+                tally.won,
+                tally.lost,
+                tally.current_streak,
+                tally.best_streak,
+                average,
+                tally.total_time
+            ));
+        }
+        if stats.iter().next().is_none() {
+            text.push_str("No games finished yet\n");
+        }
+        text.push_str("\n[j] Export JSON  [c] Export CSV  [q] Back");
+        let border_style = self.theme.active_border;
+        self.terminal
+            .draw(|f| {
+                let block = Block::default()
+                    .title("Stats")
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_style(border_style);
+                f.render_widget(
+                    Paragraph::new(Text::raw(text.as_str()))
+                        .alignment(Alignment::Center)
+                        .block(block),
+                    f.size(),
+                );
+            })
+            .unwrap();
+        loop {
+            if let Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read().unwrap()
+            {
+                match code {
+                    KeyCode::Char('j') => return Some(ExportFormat::Json),
+                    KeyCode::Char('c') => return Some(ExportFormat::Csv),
+                    KeyCode::Char('q') | KeyCode::Esc | KeyCode::Enter => return None,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn confirm(&mut self, prompt: &str) -> bool {
+        let text = format!("{}\n\n[y] Yes  [n] No", prompt);
+        let border_style = self.theme.active_border;
+        loop {
+            self.terminal
+                .draw(|f| {
+                    let block = Block::default()
+                        .title("Minesweeper")
+                        .title_alignment(Alignment::Center)
+                        .borders(Borders::ALL)
+                        .border_style(border_style);
+                    f.render_widget(
+                        Paragraph::new(Text::raw(text.as_str()))
+                            .alignment(Alignment::Center)
+                            .block(block),
+                        f.size(),
+                    );
+                })
+                .unwrap();
+            if let Event::Key(KeyEvent {
+                code,
+                kind: KeyEventKind::Press,
+                ..
+            }) = event::read().unwrap()
+            {
+                match code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => return true,
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => return false,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Splits the screen into the settings pane, board pane, status line and key bar, or `None`
+/// if it is too small to play in.
+fn layout(area: Rect) -> Option<[Rect; 4]> {
+    if area.width < 30 || area.height < 15 {
+        return None;
+    }
+    let inner_area = Block::default().borders(Borders::ALL).inner(area);
+    let bars = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(11),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(inner_area);
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(22), Constraint::Min(5)])
+        .split(bars[0]);
+    Some([panes[0], panes[1], bars[1], bars[2]])
+}
+
+/// Marks the sides of the board pane that have more of the board past them.
+fn draw_scroll_indicators(
+    f: &mut Frame<CrosstermBackend<Stdout>>,
+    area: Rect,
+    viewport: &Viewport,
+    theme: &Theme,
+) {
+    if !viewport.scrolling || area.width < 3 || area.height < 3 {
+        return;
+    }
+    let [up, down, left, right] = theme.scroll_symbols;
+    let middle_x = area.x + 1 + (viewport.margin() + viewport.width / 2) as u16;
+    let middle_y = area.y + 2 + (viewport.ruler() + viewport.height / 2) as u16;
+    let middle_x = middle_x.min(area.x + area.width - 2);
+    let middle_y = middle_y.min(area.y + area.height - 2);
+    let indicators = [
+        (viewport.more_above(), up, middle_x, area.y),
+        (
+            viewport.more_below(),
+            down,
+            middle_x,
+            area.y + area.height - 1,
+        ),
+        (viewport.more_left(), left, area.x, middle_y),
+        (
+            viewport.more_right(),
+            right,
+            area.x + area.width - 1,
+            middle_y,
+        ),
+    ];
+    for (shown, symbol, x, y) in indicators {
+        if shown {
+            let cell = Rect::new(x, y, 1, 1);
+            let symbol = Span::styled(symbol, theme.scroll_indicator);
+            f.render_widget(Paragraph::new(symbol), cell);
+        }
+    }
 }
 
 fn initialize_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>, io::Error> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange
+    )?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
@@ -492,11 +896,36 @@ fn deinitialize_terminal(
 ) -> Result<(), io::Error> {
     flush_events()?;
     terminal.show_cursor()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableMouseCapture,
+        DisableFocusChange,
+        LeaveAlternateScreen
+    )?;
     disable_raw_mode()?;
     Ok(())
 }
 
+/// Whether `event` is a key being released or held down rather than pressed. Some platforms
+/// (Windows in particular) report these too, and acting on them would repeat every key.
+fn is_release_or_repeat(event: &Event) -> bool {
+    matches!(event, Event::Key(key) if key.kind != KeyEventKind::Press)
+}
+
+/// Whether `event` happened without the player pressing anything, so it shouldn't clear the
+/// status line.
+fn is_passive(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Moved | MouseEventKind::Drag(_),
+            ..
+        }) | Event::FocusGained
+            | Event::FocusLost
+            | Event::Resize(..)
+    )
+}
+
 fn flush_events() -> Result<(), io::Error> {
     while event::poll(Duration::ZERO)? {
         event::read()?;
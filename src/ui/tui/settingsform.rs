@@ -7,9 +7,14 @@ use tui::{
     widgets::Paragraph,
 };
 
+use super::theme::Theme;
 use crate::{
-    game::{Difficulty::*, Settings},
-    CustomBoard,
+    game::{
+        CustomBoard,
+        Difficulty::{self, *},
+        Settings,
+    },
+    BoardError,
 };
 use numbox::{Action as NBAction, NumBox};
 
@@ -48,11 +53,19 @@ const DEFAULT_FIELD_STYLE: FieldStyle = FieldStyle {
     },
 };
 
+impl Default for FieldStyle {
+    fn default() -> Self {
+        DEFAULT_FIELD_STYLE
+    }
+}
+
 #[derive(Debug)]
 pub struct SettingsForm<'a> {
     settings: Settings,
     state: State,
-    focus: Option<Settings>, // None when out of focus and stores the old value when infocus.
+    theme: usize, // Index in Theme::NAMES.
+    // None when out of focus and stores the old settings and theme when in focus.
+    focus: Option<(Settings, usize)>,
     width_input: NumBox<'a>,
     height_input: NumBox<'a>,
     mines_input: NumBox<'a>,
@@ -70,6 +83,7 @@ enum State {
     FocusWidth,
     FocusHeight,
     FocusMines,
+    FocusTheme,
     EditHeight,
     EditWidth,
     EditMines,
@@ -82,6 +96,7 @@ pub enum SFAction {
     Change(Settings),
     Set(Settings),
     Reset(Settings),
+    Invalid(BoardError),
 }
 
 impl<'a> SettingsForm<'a> {
@@ -97,14 +112,16 @@ impl<'a> SettingsForm<'a> {
         let mut height_input = NumBox::new(
             "Height : ".to_string(),
             2,
-            init_settings.difficulty.width(),
+            init_settings.difficulty.height(),
             style,
         );
         height_input.set_min_bound(Some(2));
         height_input.set_max_bound(Some(99));
+        // Wide enough for the most mines any custom board can have.
+        let mines_digits = (CustomBoard::MAX_AREA - 1).to_string().len();
         let mut mines_input = NumBox::new(
             "Mines  : ".to_string(),
-            3,
+            mines_digits,
             init_settings.difficulty.mines(),
             style,
         );
@@ -112,6 +129,7 @@ impl<'a> SettingsForm<'a> {
         Self {
             settings: init_settings,
             state: State::default(),
+            theme: 0,
             focus: None,
             width_input,
             height_input,
@@ -122,23 +140,30 @@ impl<'a> SettingsForm<'a> {
     }
 
     pub fn render(&self) -> Paragraph<'a> {
-        let content = self.content_cache.borrow();
-        if content.is_some() {
-            content.as_ref().unwrap().clone()
-        } else {
+        if self.content_cache.borrow().is_none() {
             self.render_to_cache();
-            self.content_cache.borrow().as_ref().unwrap().clone()
         }
+        self.content_cache.borrow().as_ref().unwrap().clone()
     }
 
     pub fn focus(&mut self) {
-        self.focus = Some(self.settings.clone());
+        self.focus = Some((self.settings.clone(), self.theme));
         self.reset_cache();
     }
 
+    /// Whether one of the number boxes is being edited.
+    pub fn is_editing(&self) -> bool {
+        matches!(
+            self.state,
+            State::EditWidth | State::EditHeight | State::EditMines
+        )
+    }
+
+    /// Leaves the form, going back to the settings it had when it was focused.
     pub fn unfocus(&mut self) {
-        if let Some(ref old_settings) = self.focus {
-            self.settings = old_settings.clone();
+        if let Some((old_settings, old_theme)) = self.focus.take() {
+            self.settings = old_settings;
+            self.theme = old_theme;
             match &self.state {
                 State::EditWidth => {
                     self.width_input.unfocus();
@@ -166,7 +191,7 @@ impl<'a> SettingsForm<'a> {
         use KeyCode::*;
         match self.focus {
             None => SFAction::Inactive,
-            Some(ref old_settings) => {
+            Some(_) => {
                 let (width, height, mut mines) = (
                     self.settings.difficulty.width(),
                     self.settings.difficulty.height(),
@@ -176,73 +201,50 @@ impl<'a> SettingsForm<'a> {
                     State::EditWidth => match self.width_input.handle_input(event) {
                         NBAction::None => SFAction::None,
                         NBAction::Inactive => unreachable!(),
-                        NBAction::Change(width) => {
-                            self.settings.difficulty =
-                                Custom(CustomBoard::new(width, height, mines));
-                            self.reset_cache();
-                            SFAction::Change(self.settings.clone())
-                        }
+                        NBAction::Change(width) => self.set_custom(width, height, mines),
                         NBAction::Set(width) | NBAction::Reset(width) => {
                             if mines >= width * height {
-                                mines = width * height - 1;
+                                mines = (width * height).saturating_sub(1);
                                 self.mines_input.set_value(mines);
                             }
-                            self.settings.difficulty =
-                                Custom(CustomBoard::new(width, height, mines));
                             self.state = State::FocusWidth;
-                            self.reset_cache();
-                            SFAction::Change(self.settings.clone())
+                            self.set_custom(width, height, mines)
                         }
                     },
                     State::EditHeight => match self.height_input.handle_input(event) {
                         NBAction::None => SFAction::None,
                         NBAction::Inactive => unreachable!(),
-                        NBAction::Change(height) => {
-                            self.settings.difficulty =
-                                Custom(CustomBoard::new(width, height, mines));
-                            self.reset_cache();
-                            SFAction::Change(self.settings.clone())
-                        }
+                        NBAction::Change(height) => self.set_custom(width, height, mines),
                         NBAction::Set(height) | NBAction::Reset(height) => {
                             if mines >= width * height {
-                                mines = width * height - 1;
+                                mines = (width * height).saturating_sub(1);
                                 self.mines_input.set_value(mines);
                             }
-                            self.settings.difficulty =
-                                Custom(CustomBoard::new(width, height, mines));
-                            self.reset_cache();
                             self.state = State::FocusHeight;
-                            SFAction::Change(self.settings.clone())
+                            self.set_custom(width, height, mines)
                         }
                     },
                     State::EditMines => match self.mines_input.handle_input(event) {
                         NBAction::None => SFAction::None,
                         NBAction::Inactive => unreachable!(),
-                        NBAction::Change(mines) => {
-                            self.settings.difficulty =
-                                Custom(CustomBoard::new(width, height, mines));
-                            self.reset_cache();
-                            SFAction::Change(self.settings.clone())
-                        }
+                        NBAction::Change(mines) => self.set_custom(width, height, mines),
                         NBAction::Set(mines) | NBAction::Reset(mines) => {
-                            self.settings.difficulty =
-                                Custom(CustomBoard::new(width, height, mines));
-                            self.reset_cache();
                             self.state = State::FocusMines;
-                            SFAction::Change(self.settings.clone())
+                            self.set_custom(width, height, mines)
                         }
                     },
                     _ => match event {
                         Event::Key(KeyEvent { code, .. }) => match code {
                             Up => {
                                 self.state = match &self.state {
-                                    State::FocusEasy => State::FocusMines,
+                                    State::FocusEasy => State::FocusTheme,
                                     State::FocusMedium => State::FocusEasy,
                                     State::FocusHard => State::FocusMedium,
                                     State::FocusCustom => State::FocusHard,
                                     State::FocusWidth => State::FocusCustom,
                                     State::FocusHeight => State::FocusWidth,
                                     State::FocusMines => State::FocusHeight,
+                                    State::FocusTheme => State::FocusMines,
                                     State::EditHeight | State::EditWidth | State::EditMines => {
                                         unreachable!()
                                     }
@@ -258,7 +260,8 @@ impl<'a> SettingsForm<'a> {
                                     State::FocusCustom => State::FocusWidth,
                                     State::FocusWidth => State::FocusHeight,
                                     State::FocusHeight => State::FocusMines,
-                                    State::FocusMines => State::FocusEasy,
+                                    State::FocusMines => State::FocusTheme,
+                                    State::FocusTheme => State::FocusEasy,
                                     State::EditHeight | State::EditWidth | State::EditMines => {
                                         unreachable!()
                                     }
@@ -285,15 +288,11 @@ impl<'a> SettingsForm<'a> {
                                     self.reset_cache();
                                     SFAction::Change(self.settings.clone())
                                 }
-                                State::FocusCustom => {
-                                    self.settings.difficulty =
-                                        Custom(CustomBoard::new(width, height, mines));
-                                    self.reset_cache();
-                                    SFAction::Change(self.settings.clone())
-                                }
+                                State::FocusCustom => self.set_custom(width, height, mines),
                                 State::FocusWidth | State::FocusHeight | State::FocusMines => {
                                     SFAction::None
                                 }
+                                State::FocusTheme => self.cycle_theme(1),
                                 State::EditHeight | State::EditWidth | State::EditMines => {
                                     unreachable!()
                                 }
@@ -302,7 +301,10 @@ impl<'a> SettingsForm<'a> {
                                 State::FocusEasy
                                 | State::FocusMedium
                                 | State::FocusHard
-                                | State::FocusCustom => SFAction::None,
+                                | State::FocusCustom
+                                | State::FocusTheme => {
+                                    self.handle_input(Event::Key(KeyEvent::from(Char(' '))))
+                                }
                                 State::FocusWidth => {
                                     self.state = State::EditWidth;
                                     self.width_input.focus();
@@ -327,18 +329,29 @@ impl<'a> SettingsForm<'a> {
                                 }
                             },
                             Char('w') => {
+                                // The boxes can still hold a board that failed to apply.
+                                if let Custom(_) = self.settings.difficulty {
+                                    if let Err(err) = Difficulty::try_custom(
+                                        self.width_input.value(),
+                                        self.height_input.value(),
+                                        self.mines_input.value(),
+                                    ) {
+                                        return SFAction::Invalid(err);
+                                    }
+                                }
                                 self.focus = None;
                                 self.reset_cache();
                                 SFAction::Set(self.settings.clone())
                             }
+                            Left if self.state == State::FocusTheme => {
+                                self.cycle_theme(Theme::NAMES.len() - 1)
+                            }
+                            Right if self.state == State::FocusTheme => self.cycle_theme(1),
                             Char('q') | Esc => {
-                                self.settings = old_settings.clone();
-                                self.focus = None;
-                                self.update_hwm();
-                                self.reset_cache();
+                                self.unfocus();
                                 SFAction::Reset(self.settings.clone())
                             }
-                            _ => todo!(),
+                            _ => SFAction::None,
                         },
                         _ => SFAction::None,
                     },
@@ -347,6 +360,31 @@ impl<'a> SettingsForm<'a> {
         }
     }
 
+    /// Moves to the option on `line` of the rendered form and chooses it, or starts editing
+    /// it if it is a number.
+    pub fn click(&mut self, line: usize) -> SFAction {
+        let state = match line {
+            1 => State::FocusEasy,
+            2 => State::FocusMedium,
+            3 => State::FocusHard,
+            4 => State::FocusCustom,
+            5 => State::FocusWidth,
+            6 => State::FocusHeight,
+            7 => State::FocusMines,
+            8 => State::FocusTheme,
+            _ => return SFAction::None,
+        };
+        if self.focus.is_none() {
+            return SFAction::Inactive;
+        }
+        if let State::EditWidth | State::EditHeight | State::EditMines = self.state {
+            return SFAction::None;
+        }
+        self.state = state;
+        self.reset_cache();
+        self.handle_input(Event::Key(KeyEvent::from(KeyCode::Enter)))
+    }
+
     pub fn set(&mut self, settings: Settings) {
         self.settings = settings;
         self.width_input.set_value(self.settings.difficulty.width());
@@ -356,12 +394,36 @@ impl<'a> SettingsForm<'a> {
         self.reset_cache();
     }
 
+    /// The name of the theme chosen in the form.
+    pub fn theme(&self) -> &'static str {
+        Theme::NAMES[self.theme]
+    }
+
+    pub fn set_theme(&mut self, name: &str) {
+        self.theme = Theme::NAMES
+            .iter()
+            .position(|&theme| theme == name)
+            .unwrap_or(0);
+        self.reset_cache();
+    }
+
     pub fn set_style(&mut self, style: FieldStyle) {
         self.style = style;
+        self.width_input.set_style(style);
+        self.height_input.set_style(style);
+        self.mines_input.set_style(style);
         self.reset_cache();
     }
 
+    fn cycle_theme(&mut self, step: usize) -> SFAction {
+        self.theme = (self.theme + step) % Theme::NAMES.len();
+        self.reset_cache();
+        SFAction::Change(self.settings.clone())
+    }
+
     fn render_to_cache(&self) {
+        // Only show the cursor while the form is being edited.
+        let state = self.focus.as_ref().map(|_| self.state);
         let mut lines = Vec::new();
         lines.push(Spans(vec![Span::styled(
             "Difficulty".to_string(),
@@ -375,7 +437,7 @@ impl<'a> SettingsForm<'a> {
             "Easy   ".to_string(),
             self.style,
             self.settings.difficulty == Easy,
-            self.state == State::FocusEasy,
+            state == Some(State::FocusEasy),
         ));
 
         // Medium
@@ -383,7 +445,7 @@ impl<'a> SettingsForm<'a> {
             "Medium  ".to_string(),
             self.style,
             self.settings.difficulty == Medium,
-            self.state == State::FocusMedium,
+            state == Some(State::FocusMedium),
         ));
 
         // Hard
@@ -391,7 +453,7 @@ impl<'a> SettingsForm<'a> {
             "Hard   ".to_string(),
             self.style,
             self.settings.difficulty == Hard,
-            self.state == State::FocusHard,
+            state == Some(State::FocusHard),
         ));
 
         // Custom
@@ -401,13 +463,13 @@ impl<'a> SettingsForm<'a> {
             self.settings.difficulty != Easy
                 && self.settings.difficulty != Medium
                 && self.settings.difficulty != Hard,
-            self.state == State::FocusCustom,
+            state == Some(State::FocusCustom),
         ));
 
         //Width
         lines.push({
             let mut spans = self.width_input.render();
-            if self.state == State::FocusWidth || self.state == State::EditWidth {
+            if state == Some(State::FocusWidth) || state == Some(State::EditWidth) {
                 patch_spans(&mut spans, self.style.selected)
             }
             spans
@@ -416,7 +478,7 @@ impl<'a> SettingsForm<'a> {
         //Height
         lines.push({
             let mut spans = self.height_input.render();
-            if self.state == State::FocusHeight || self.state == State::EditHeight {
+            if state == Some(State::FocusHeight) || state == Some(State::EditHeight) {
                 patch_spans(&mut spans, self.style.selected)
             }
             spans
@@ -425,12 +487,22 @@ impl<'a> SettingsForm<'a> {
         //Mines
         lines.push({
             let mut spans = self.mines_input.render();
-            if self.state == State::FocusMines || self.state == State::EditMines {
+            if state == Some(State::FocusMines) || state == Some(State::EditMines) {
                 patch_spans(&mut spans, self.style.selected)
             }
             spans
         });
 
+        //Theme
+        lines.push({
+            let (label_style, field_style) =
+                get_styles(self.style, state == Some(State::FocusTheme));
+            Spans(vec![
+                Span::styled("Theme: ", label_style),
+                Span::styled(self.theme(), field_style),
+            ])
+        });
+
         *(self.content_cache.borrow_mut()) = Some(Paragraph::new(Text { lines }));
     }
 
@@ -438,6 +510,17 @@ impl<'a> SettingsForm<'a> {
         *(self.content_cache.borrow_mut()) = None;
     }
 
+    fn set_custom(&mut self, width: usize, height: usize, mines: usize) -> SFAction {
+        self.reset_cache();
+        match Difficulty::try_custom(width, height, mines) {
+            Ok(difficulty) => {
+                self.settings.difficulty = difficulty;
+                SFAction::Change(self.settings.clone())
+            }
+            Err(err) => SFAction::Invalid(err),
+        }
+    }
+
     fn update_hwm(&mut self) {
         let (width, height, mines) = (
             self.settings.difficulty.width(),
@@ -552,13 +635,10 @@ mod numbox {
         }
 
         pub fn render(&self) -> Spans<'a> {
-            let content = self.content_cache.borrow();
-            if content.is_some() {
-                content.as_ref().unwrap().clone()
-            } else {
+            if self.content_cache.borrow().is_none() {
                 self.render_to_cache();
-                self.content_cache.borrow().as_ref().unwrap().clone()
             }
+            self.content_cache.borrow().as_ref().unwrap().clone()
         }
 
         fn render_to_cache(&self) {
@@ -588,6 +668,10 @@ mod numbox {
             *(self.content_cache.borrow_mut()) = None;
         }
 
+        pub fn value(&self) -> usize {
+            self.value
+        }
+
         pub fn set_value(&mut self, value: usize) {
             self.value = value;
             self.reset_cache();
@@ -653,13 +737,15 @@ mod numbox {
                             Action::Change(self.value)
                         }
                         Down => {
-                            self.value = (self.value - 10usize.pow(*cursor as u32))
-                                % (10usize.pow(self.digits as u32));
+                            let modulus = 10usize.pow(self.digits as u32);
+                            self.value =
+                                (self.value + modulus - 10usize.pow(*cursor as u32)) % modulus;
                             self.reset_cache();
                             Action::Change(self.value)
                         }
                         Left => {
                             *cursor = (*cursor + 1) % self.digits;
+                            self.reset_cache();
                             Action::None
                         }
                         Right => {
@@ -668,12 +754,13 @@ mod numbox {
                             } else {
                                 *cursor - 1
                             };
+                            self.reset_cache();
                             Action::None
                         }
                         Char(c) if c.is_ascii_digit() => {
                             let digit = c.to_digit(10).unwrap() as usize;
                             let place_value = 10usize.pow(*cursor as u32);
-                            self.value -= (self.value / place_value) % 10;
+                            self.value -= ((self.value / place_value) % 10) * place_value;
                             self.value += digit * place_value;
                             *cursor = cursor.saturating_sub(1);
                             self.reset_cache();
@@ -690,7 +777,8 @@ mod numbox {
                                     self.value = min;
                                 }
                             }
-                            self.unfocus();
+                            self.state = Inactive;
+                            self.reset_cache();
                             Action::Set(self.value)
                         }
                         Esc => {
@@ -706,4 +794,71 @@ mod numbox {
             }
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn press(numbox: &mut NumBox, code: KeyCode) -> Action {
+            numbox.handle_input(Event::Key(KeyEvent::from(code)))
+        }
+
+        fn cursor(numbox: &NumBox) -> usize {
+            match numbox.state {
+                Active(ActiveState { cursor, .. }) => cursor,
+                Inactive => panic!("NumBox isn't focused"),
+            }
+        }
+
+        #[test]
+        fn typing_replaces_digits() {
+            let mut numbox = NumBox::new(String::new(), 2, 19, FieldStyle::default());
+            numbox.focus();
+            assert_eq!(press(&mut numbox, KeyCode::Char('3')), Action::Change(39));
+            assert_eq!(press(&mut numbox, KeyCode::Char('0')), Action::Change(30));
+            assert_eq!(press(&mut numbox, KeyCode::Char('7')), Action::Change(37));
+        }
+
+        #[test]
+        fn arrows_move_cursor_and_change_digits() {
+            let mut numbox = NumBox::new(String::new(), 3, 105, FieldStyle::default());
+            numbox.focus();
+            assert_eq!(cursor(&numbox), 2);
+            assert_eq!(press(&mut numbox, KeyCode::Right), Action::None);
+            assert_eq!(cursor(&numbox), 1);
+            assert_eq!(press(&mut numbox, KeyCode::Up), Action::Change(115));
+            assert_eq!(press(&mut numbox, KeyCode::Down), Action::Change(105));
+            assert_eq!(press(&mut numbox, KeyCode::Down), Action::Change(95));
+            assert_eq!(press(&mut numbox, KeyCode::Left), Action::None);
+            assert_eq!(press(&mut numbox, KeyCode::Left), Action::None);
+            assert_eq!(cursor(&numbox), 0);
+        }
+
+        #[test]
+        fn moving_the_cursor_redraws() {
+            let mut numbox = NumBox::new(String::new(), 2, 19, FieldStyle::default());
+            numbox.focus();
+            let before = numbox.render();
+            press(&mut numbox, KeyCode::Right);
+            assert_ne!(numbox.render(), before);
+        }
+
+        #[test]
+        fn enter_clamps_and_esc_restores() {
+            let mut numbox = NumBox::new(String::new(), 2, 19, FieldStyle::default());
+            numbox.set_min_bound(Some(2));
+            numbox.set_max_bound(Some(50));
+            numbox.focus();
+            press(&mut numbox, KeyCode::Char('9'));
+            assert_eq!(press(&mut numbox, KeyCode::Enter), Action::Set(50));
+            numbox.focus();
+            press(&mut numbox, KeyCode::Char('0'));
+            press(&mut numbox, KeyCode::Char('0'));
+            assert_eq!(press(&mut numbox, KeyCode::Enter), Action::Set(2));
+            numbox.focus();
+            press(&mut numbox, KeyCode::Char('4'));
+            assert_eq!(press(&mut numbox, KeyCode::Esc), Action::Reset(2));
+            assert_eq!(numbox.value(), 2);
+        }
+    }
 }
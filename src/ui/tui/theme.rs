@@ -0,0 +1,273 @@
+use tui::style::{Color, Modifier, Style};
+
+use super::settingsform::FieldStyle;
+
+/// Every colour and symbol the TUI draws with.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: &'static str,
+    pub active_border: Style,
+    pub inactive_border: Style,
+    /// Keys in the key bar.
+    pub key: Style,
+    pub mine_counter: Style,
+    pub timer: Style,
+    /// Row and column numbers around a board that doesn't fit.
+    pub coordinates: Style,
+    pub scroll_indicator: Style,
+    /// Up, down, left and right.
+    pub scroll_symbols: [&'static str; 4],
+    pub paused: Style,
+    pub cells: CellStyle,
+    pub field: FieldStyle,
+}
+
+/// How the cells of the board are drawn. The style for where a cell is gets patched with the
+/// one for what it holds, then the one for a hint.
+#[derive(Debug, Clone)]
+pub struct CellStyle {
+    pub hidden: &'static str,
+    pub flag: (&'static str, Style),
+    pub mine: (&'static str, Style),
+    pub false_flag: (&'static str, Style),
+    pub tripped_mine: (&'static str, Style),
+    /// The styles of 1 to 8.
+    pub numbers: [Style; 8],
+    pub closed: Style,
+    pub opened: Style,
+    pub closed_near_cursor: Style,
+    pub opened_near_cursor: Style,
+    pub cursor: Style,
+    pub hint_safe: Style,
+    pub hint_guess: Style,
+    /// Added to the mine that was hit and flags that were wrong.
+    pub mistake: Style,
+}
+
+impl Theme {
+    /// The names of the built-in themes, in the order the settings pane cycles through them.
+    pub const NAMES: [&'static str; 4] = ["classic", "high-contrast", "colourblind", "mono"];
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Theme::classic()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "colourblind" => Some(Theme::colourblind()),
+            "mono" => Some(Theme::mono()),
+            _ => None,
+        }
+    }
+
+    pub fn classic() -> Self {
+        let fg = |color| Style::default().fg(color);
+        let bg = |color| Style::default().bg(color);
+        Theme {
+            name: "classic",
+            active_border: fg(Color::White),
+            inactive_border: Style::default().add_modifier(Modifier::DIM),
+            key: Style::default().add_modifier(Modifier::BOLD),
+            mine_counter: fg(Color::Blue),
+            timer: fg(Color::Red),
+            coordinates: Style::default().add_modifier(Modifier::DIM),
+            scroll_indicator: fg(Color::Yellow),
+            scroll_symbols: ["▲", "▼", "◀", "▶"],
+            paused: Style::default()
+                .fg(Color::Black)
+                .bg(Color::Gray)
+                .add_modifier(Modifier::BOLD),
+            cells: CellStyle {
+                hidden: " ",
+                flag: ("►", fg(Color::LightGreen)),
+                mine: ("*", fg(Color::Green)),
+                false_flag: ("►", fg(Color::Red)),
+                tripped_mine: ("*", fg(Color::Red)),
+                numbers: [
+                    fg(Color::Blue),
+                    fg(Color::LightGreen),
+                    fg(Color::LightRed),
+                    fg(Color::LightBlue),
+                    fg(Color::Cyan),
+                    fg(Color::LightMagenta),
+                    fg(Color::LightCyan),
+                    fg(Color::Magenta),
+                ],
+                closed: bg(Color::Gray),
+                opened: bg(Color::DarkGray),
+                closed_near_cursor: bg(Color::LightYellow),
+                opened_near_cursor: bg(Color::Yellow),
+                cursor: bg(Color::White),
+                hint_safe: bg(Color::LightCyan),
+                hint_guess: bg(Color::LightMagenta),
+                mistake: Style::default().add_modifier(Modifier::RAPID_BLINK),
+            },
+            field: FieldStyle::default(),
+        }
+    }
+
+    /// Bold, bright colours on black and white, for low-contrast screens.
+    pub fn high_contrast() -> Self {
+        let bold = |color| Style::default().fg(color).add_modifier(Modifier::BOLD);
+        let bg = |color| Style::default().bg(color);
+        let classic = Theme::classic();
+        Theme {
+            name: "high-contrast",
+            active_border: bold(Color::Yellow),
+            inactive_border: Style::default().fg(Color::Gray),
+            key: bold(Color::Yellow),
+            mine_counter: bold(Color::LightCyan),
+            timer: bold(Color::LightRed),
+            coordinates: Style::default().fg(Color::White),
+            scroll_indicator: bold(Color::Yellow),
+            paused: bold(Color::White).bg(Color::Black),
+            cells: CellStyle {
+                flag: ("►", bold(Color::Red)),
+                mine: ("*", bold(Color::White)),
+                false_flag: ("X", bold(Color::LightRed)),
+                tripped_mine: ("*", bold(Color::White).bg(Color::Red)),
+                numbers: [
+                    bold(Color::LightBlue),
+                    bold(Color::LightGreen),
+                    bold(Color::LightRed),
+                    bold(Color::LightMagenta),
+                    bold(Color::LightYellow),
+                    bold(Color::LightCyan),
+                    bold(Color::White),
+                    bold(Color::Gray),
+                ],
+                closed: bg(Color::White),
+                opened: bg(Color::Black),
+                closed_near_cursor: bg(Color::LightCyan),
+                opened_near_cursor: bg(Color::Blue),
+                cursor: bg(Color::Yellow),
+                hint_safe: bg(Color::Green),
+                hint_guess: bg(Color::Magenta),
+                ..classic.cells
+            },
+            field: FieldStyle {
+                selected: Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+                ..FieldStyle::default()
+            },
+            ..classic
+        }
+    }
+
+    /// The Okabe-Ito palette, which stays distinct with any kind of colour blindness. Red
+    /// and green are never the only difference between two things.
+    pub fn colourblind() -> Self {
+        const BLUE: Color = Color::Rgb(0, 114, 178);
+        const ORANGE: Color = Color::Rgb(230, 159, 0);
+        const SKY_BLUE: Color = Color::Rgb(86, 180, 233);
+        const BLUISH_GREEN: Color = Color::Rgb(0, 158, 115);
+        const YELLOW: Color = Color::Rgb(240, 228, 66);
+        const VERMILLION: Color = Color::Rgb(213, 94, 0);
+        const REDDISH_PURPLE: Color = Color::Rgb(204, 121, 167);
+        let fg = |color| Style::default().fg(color);
+        let bg = |color| Style::default().bg(color);
+        let classic = Theme::classic();
+        Theme {
+            name: "colourblind",
+            mine_counter: fg(SKY_BLUE),
+            timer: fg(ORANGE),
+            scroll_indicator: fg(YELLOW),
+            cells: CellStyle {
+                flag: ("►", fg(BLUE)),
+                mine: ("*", fg(Color::White)),
+                false_flag: ("X", fg(VERMILLION)),
+                tripped_mine: ("*", fg(Color::White).bg(VERMILLION)),
+                numbers: [
+                    fg(SKY_BLUE),
+                    fg(ORANGE),
+                    fg(REDDISH_PURPLE),
+                    fg(BLUISH_GREEN),
+                    fg(YELLOW),
+                    fg(VERMILLION),
+                    fg(Color::White),
+                    fg(Color::Gray),
+                ],
+                closed_near_cursor: bg(Color::Rgb(250, 240, 150)),
+                opened_near_cursor: bg(Color::Rgb(90, 90, 90)),
+                hint_safe: bg(SKY_BLUE),
+                hint_guess: bg(ORANGE),
+                ..classic.cells
+            },
+            ..classic
+        }
+    }
+
+    /// No colours and only ASCII on the board, for terminals without either.
+    pub fn mono() -> Self {
+        let plain = Style::default();
+        let with = |modifier| Style::default().add_modifier(modifier);
+        let classic = Theme::classic();
+        Theme {
+            name: "mono",
+            active_border: with(Modifier::BOLD),
+            mine_counter: plain,
+            timer: plain,
+            scroll_indicator: with(Modifier::BOLD),
+            scroll_symbols: ["^", "v", "<", ">"],
+            paused: with(Modifier::BOLD),
+            cells: CellStyle {
+                hidden: ".",
+                flag: ("F", with(Modifier::BOLD)),
+                mine: ("*", plain),
+                false_flag: ("X", with(Modifier::BOLD)),
+                tripped_mine: ("#", with(Modifier::BOLD)),
+                numbers: [plain; 8],
+                closed: plain,
+                opened: plain,
+                closed_near_cursor: plain,
+                opened_near_cursor: plain,
+                cursor: with(Modifier::REVERSED),
+                hint_safe: with(Modifier::UNDERLINED),
+                hint_guess: with(Modifier::UNDERLINED | Modifier::ITALIC),
+                mistake: plain,
+            },
+            ..classic
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        for name in Theme::NAMES {
+            assert_eq!(Theme::named(name).unwrap().name, name);
+        }
+        assert!(Theme::named("solarized").is_none());
+    }
+
+    #[test]
+    fn mono_is_ascii_without_colour() {
+        let theme = Theme::mono();
+        let cells = &theme.cells;
+        let symbols = [
+            cells.hidden,
+            cells.flag.0,
+            cells.mine.0,
+            cells.false_flag.0,
+            cells.tripped_mine.0,
+        ];
+        assert!(symbols
+            .iter()
+            .chain(&theme.scroll_symbols)
+            .all(|symbol| symbol.is_ascii()));
+        let styles = [cells.closed, cells.opened, cells.cursor, theme.timer];
+        assert!(styles
+            .iter()
+            .chain(&cells.numbers)
+            .all(|style| style.fg.is_none() && style.bg.is_none()));
+    }
+}
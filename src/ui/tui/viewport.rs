@@ -0,0 +1,138 @@
+use std::ops::Range;
+
+/// The part of the board that fits in the board pane.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Viewport {
+    /// The first column and row shown.
+    pub x: usize,
+    pub y: usize,
+    /// How many columns and rows are shown.
+    pub width: usize,
+    pub height: usize,
+    pub board_width: usize,
+    pub board_height: usize,
+    /// Whether the board is bigger than the pane. Coordinates are only drawn around the
+    /// board then, in the space `margin` and `ruler` take.
+    pub scrolling: bool,
+}
+
+impl Viewport {
+    /// Fits a board into `width` by `height` cells, scrolling as little as possible from
+    /// where `self` was to keep `cursor` in view.
+    pub fn fit(
+        self,
+        (board_width, board_height): (usize, usize),
+        (width, height): (usize, usize),
+        (cursor_x, cursor_y): (usize, usize),
+    ) -> Self {
+        let scrolling = board_width > width || board_height > height;
+        let mut viewport = Viewport {
+            board_width,
+            board_height,
+            scrolling,
+            ..self
+        };
+        let (width, height) = if scrolling {
+            (
+                width.saturating_sub(viewport.margin()),
+                height.saturating_sub(viewport.ruler()),
+            )
+        } else {
+            (width, height)
+        };
+        viewport.width = width.min(board_width);
+        viewport.height = height.min(board_height);
+        viewport.x = follow(self.x, viewport.width, cursor_x, board_width);
+        viewport.y = follow(self.y, viewport.height, cursor_y, board_height);
+        viewport
+    }
+
+    /// Width of the row numbers to the left of the board.
+    pub fn margin(&self) -> usize {
+        if self.scrolling {
+            self.board_height.saturating_sub(1).to_string().len() + 1
+        } else {
+            0
+        }
+    }
+
+    /// Height of the column numbers above the board.
+    pub fn ruler(&self) -> usize {
+        self.scrolling as usize
+    }
+
+    pub fn columns(&self) -> Range<usize> {
+        self.x..self.x + self.width
+    }
+
+    pub fn rows(&self) -> Range<usize> {
+        self.y..self.y + self.height
+    }
+
+    pub fn more_left(&self) -> bool {
+        self.x > 0
+    }
+
+    pub fn more_right(&self) -> bool {
+        self.x + self.width < self.board_width
+    }
+
+    pub fn more_above(&self) -> bool {
+        self.y > 0
+    }
+
+    pub fn more_below(&self) -> bool {
+        self.y + self.height < self.board_height
+    }
+}
+
+/// The offset closest to `offset` that shows `position` in a window of `size` over `length`.
+fn follow(offset: usize, size: usize, position: usize, length: usize) -> usize {
+    if size == 0 || length <= size {
+        return 0;
+    }
+    let position = position.min(length - 1);
+    offset
+        .clamp((position + 1).saturating_sub(size), position)
+        .min(length - size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_boards_fit() {
+        let viewport = Viewport::default().fit((9, 9), (40, 20), (8, 8));
+        assert!(!viewport.scrolling);
+        assert_eq!((viewport.x, viewport.y), (0, 0));
+        assert_eq!((viewport.width, viewport.height), (9, 9));
+        assert_eq!(viewport.margin(), 0);
+        assert!(!viewport.more_right() && !viewport.more_below());
+    }
+
+    #[test]
+    fn follows_the_cursor() {
+        let board = (99, 99);
+        let pane = (40, 20);
+        let viewport = Viewport::default().fit(board, pane, (0, 0));
+        assert!(viewport.scrolling);
+        assert_eq!(viewport.margin(), 3);
+        assert_eq!((viewport.width, viewport.height), (37, 19));
+        assert!(!viewport.more_left() && viewport.more_right());
+
+        // Moving inside the view doesn't scroll.
+        let moved = viewport.fit(board, pane, (36, 18));
+        assert_eq!((moved.x, moved.y), (0, 0));
+        // Going past the edge scrolls just enough.
+        let moved = moved.fit(board, pane, (37, 19));
+        assert_eq!((moved.x, moved.y), (1, 1));
+        let moved = moved.fit(board, pane, (98, 98));
+        assert_eq!(moved.columns(), 62..99);
+        assert_eq!(moved.rows(), 80..99);
+        assert!(moved.more_left() && moved.more_above());
+        assert!(!moved.more_right() && !moved.more_below());
+        let moved = moved.fit(board, pane, (0, 50));
+        assert_eq!((moved.x, moved.y), (0, 50));
+    }
+}